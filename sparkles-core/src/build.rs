@@ -0,0 +1,48 @@
+//! Build script helpers, enabled with `build` feature.
+//!
+//! Call [`emit_build_info`] from `build.rs` of your crate, and pass `sparkles::build_info!()` to `SparklesConfig::with_build_info`.
+
+use alloc::string::{String, ToString};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::{env, println};
+
+/// Environment variable with git revision of the crate, set by [`emit_build_info`]
+pub const GIT_REVISION_ENV: &str = "SPARKLES_GIT_REVISION";
+
+/// Set `SPARKLES_GIT_REVISION` for the crate, which is being built.
+///
+/// Build script is rerun when git HEAD or index is changed, or when files in `src` are modified.
+pub fn emit_build_info() {
+    let manifest_dir = PathBuf::from(env::var_os("CARGO_MANIFEST_DIR").unwrap());
+    println!("cargo:rerun-if-changed={}", manifest_dir.join("src").display());
+    println!("cargo:rerun-if-changed=build.rs");
+
+    let Some(git_dir) = git(&manifest_dir, &["rev-parse", "--absolute-git-dir"]) else {
+        return;
+    };
+    let git_dir = PathBuf::from(git_dir);
+    println!("cargo:rerun-if-changed={}", git_dir.join("HEAD").display());
+    println!("cargo:rerun-if-changed={}", git_dir.join("index").display());
+    if let Some(head_ref) = git(&manifest_dir, &["symbolic-ref", "-q", "HEAD"]) {
+        println!("cargo:rerun-if-changed={}", git_dir.join(head_ref).display());
+    }
+
+    if let Some(mut revision) = git(&manifest_dir, &["rev-parse", "--short=12", "HEAD"]) {
+        let is_dirty = git(&manifest_dir, &["status", "--porcelain", "--untracked-files=no"])
+            .is_some_and(|status| !status.is_empty());
+        if is_dirty {
+            revision.push_str("-dirty");
+        }
+        println!("cargo:rustc-env={}={}", GIT_REVISION_ENV, revision);
+    }
+}
+
+/// Run git command and return trimmed output, if succeeded
+fn git(dir: &Path, args: &[&str]) -> Option<String> {
+    let output = Command::new("git").args(args).current_dir(dir).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    String::from_utf8(output.stdout).ok().map(|s| s.trim().to_string())
+}
@@ -9,6 +9,28 @@ pub struct LocalStorageConfig {
     /// 
     /// Default: 1MB
     pub flush_threshold: usize,
+    /// Process-wide limit for the total capacity of all thread-local buffers in bytes. `None` means no limit.
+    ///
+    /// When a growing buffer exceeds the limit, `buffers_limit_policy` is applied, and the buffer is shrunk back to
+    /// `flush_attempt_threshold` bytes. So every thread may still hold about `flush_attempt_threshold` bytes.
+    ///
+    /// Default: None
+    pub total_buffers_limit: Option<usize>,
+    /// What to do with the thread-local buffer when `total_buffers_limit` is reached
+    ///
+    /// Default: Block
+    pub buffers_limit_policy: BuffersLimitPolicy,
+}
+
+/// Action, applied to the thread-local buffer when the total limit for thread-local buffers is reached
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BuffersLimitPolicy {
+    /// Flush the buffer to the global storage. Thread will be blocked until the flushing operation is finished
+    Block,
+    /// Flush the buffer only if the global storage is available at the moment, otherwise keep recording over the limit
+    FlushEarly,
+    /// Drop buffered events. Time range of the lost events is reported to the parser
+    Drop,
 }
 
 impl LocalStorageConfig {
@@ -17,6 +39,8 @@ impl LocalStorageConfig {
         Self {
             flush_attempt_threshold: 32*1024,
             flush_threshold: 1024*1024,
+            total_buffers_limit: None,
+            buffers_limit_policy: BuffersLimitPolicy::Block,
         }
     }
 }
@@ -0,0 +1,74 @@
+//! Event hashing: 32-bit FNV-1a, evaluated at compile time by the event macros and at runtime for dynamic names.
+//!
+//! The algorithm is fixed, so the same key produces the same hash in any build, unlike `DefaultHasher`.
+
+const FNV_OFFSET_BASIS: u32 = 0x811c_9dc5;
+const FNV_PRIME: u32 = 0x0100_0193;
+
+/// Incremental FNV-1a hasher, usable in const context
+#[derive(Copy, Clone, Debug)]
+pub struct EventHasher {
+    state: u32,
+}
+
+impl EventHasher {
+    pub const fn new() -> Self {
+        Self {
+            state: FNV_OFFSET_BASIS,
+        }
+    }
+
+    /// Feed string bytes
+    #[must_use]
+    pub const fn str(self, s: &str) -> Self {
+        self.bytes(s.as_bytes())
+    }
+
+    #[must_use]
+    pub const fn bytes(mut self, bytes: &[u8]) -> Self {
+        let mut i = 0;
+        while i < bytes.len() {
+            self.state ^= bytes[i] as u32;
+            self.state = self.state.wrapping_mul(FNV_PRIME);
+            i += 1;
+        }
+        self
+    }
+
+    /// Feed little-endian bytes of the number
+    #[must_use]
+    pub const fn u32(self, value: u32) -> Self {
+        self.bytes(&value.to_le_bytes())
+    }
+
+    pub const fn finish(self) -> u32 {
+        self.state
+    }
+}
+
+impl Default for EventHasher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Hash of the event name or the other event key
+pub const fn event_hash(s: &str) -> u32 {
+    EventHasher::new().str(s).finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fnv1a_reference_values() {
+        assert_eq!(event_hash(""), 0x811c9dc5);
+        assert_eq!(event_hash("a"), 0xe40c292c);
+        assert_eq!(event_hash("foobar"), 0xbf9cf968);
+        assert_eq!(EventHasher::new().str("foo").str("bar").finish(), event_hash("foobar"));
+
+        const HASH: u32 = event_hash("foobar");
+        assert_eq!(HASH, 0xbf9cf968);
+    }
+}
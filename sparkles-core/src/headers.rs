@@ -1,4 +1,5 @@
 use alloc::string::{String, ToString};
+use alloc::vec::Vec;
 use serde::{Deserialize, Serialize};
 use crate::local_storage::id_mapping::IdMapping;
 use crate::{Timestamp, TimestampProvider};
@@ -25,12 +26,56 @@ pub struct ThreadInfo {
     pub new_thread_name: Option<String>,
 }
 
+/// Version of the encoder info layout.
+/// New fields are only appended to the end, so older parsers ignore them
+///
+/// - 0: no build info
+/// - 1: `build_info`
+/// - 2: `info_ver` and `process_info`
+pub const ENCODER_INFO_VERSION: u32 = 2;
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct SparklesEncoderInfo {
     pub ver: u32,
     pub process_name: String,
     pub pid: u32,
-    pub timestamp_max_value: u64
+    pub timestamp_max_value: u64,
+    pub build_info: BuildInfo,
+    /// Layout version, see [`ENCODER_INFO_VERSION`]
+    pub info_ver: u32,
+    pub process_info: ProcessInfo,
+}
+
+/// Information about the traced process and the host, where it was running
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct ProcessInfo {
+    pub cmdline: Vec<String>,
+    pub hostname: Option<String>,
+    /// OS family, like `linux` or `windows`
+    pub os: String,
+    /// OS name and version, like `Ubuntu 24.04.1 LTS`
+    pub os_version: Option<String>,
+    pub kernel_version: Option<String>,
+    pub cpu_model: Option<String>,
+    pub logical_cores: u32,
+    /// Wall-clock time of the capture session start, in nanoseconds since UNIX epoch
+    pub start_time_unix_ns: u64,
+}
+
+/// Information about the build, which produced the trace
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct BuildInfo {
+    /// Name of the traced crate
+    pub crate_name: Option<String>,
+    pub crate_version: Option<String>,
+    /// Git revision of the traced crate, with `-dirty` suffix if there were uncommitted changes
+    pub git_revision: Option<String>,
+    /// Cargo profile: `debug` or `release`
+    pub profile: String,
+    /// Target triple
+    pub target: String,
+    /// Enabled features of the sparkles crate
+    pub sparkles_features: Vec<String>,
 }
 
 impl SparklesEncoderInfo {
@@ -40,6 +85,9 @@ impl SparklesEncoderInfo {
             process_name,
             ver: crate::consts::ENCODER_VERSION,
             timestamp_max_value: Timestamp::MAX_VALUE,
+            build_info: BuildInfo::default(),
+            info_ver: ENCODER_INFO_VERSION,
+            process_info: ProcessInfo::default(),
         }
     }
 }
@@ -51,6 +99,9 @@ impl Default for SparklesEncoderInfo {
             pid: 0,
             ver: crate::consts::ENCODER_VERSION,
             timestamp_max_value: Timestamp::MAX_VALUE,
+            build_info: BuildInfo::default(),
+            info_ver: ENCODER_INFO_VERSION,
+            process_info: ProcessInfo::default(),
         }
     }
 }
\ No newline at end of file
@@ -1,5 +1,7 @@
 #![no_std]
 extern crate alloc;
+#[cfg(feature = "build")]
+extern crate std;
 
 pub mod timestamp;
 pub use timestamp::{Timestamp, TimestampProvider};
@@ -8,4 +10,7 @@ pub mod local_storage;
 pub mod headers;
 pub mod config;
 pub mod sender;
-pub mod consts;
\ No newline at end of file
+pub mod consts;
+pub mod hash;
+#[cfg(feature = "build")]
+pub mod build;
\ No newline at end of file
@@ -0,0 +1,107 @@
+//! Numeric values attached to the events.
+//!
+//! Encoded format: `[count] ([kind << 4 | len] [len bytes of LE value])*`
+//!
+//! Unsigned and signed values are stored with the minimal amount of bytes (signed values are zigzag-encoded),
+//! floating point values always take 8 bytes. At most `MAX_ARGS` arguments can be attached to an event.
+
+use alloc::vec::Vec;
+
+pub const ARG_KIND_UNSIGNED: u8 = 0;
+pub const ARG_KIND_SIGNED: u8 = 1;
+pub const ARG_KIND_FLOAT: u8 = 2;
+
+/// Max amount of arguments, attached to a single event
+pub const MAX_ARGS: usize = u8::MAX as usize;
+
+/// Single numeric value, attached to an event
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum EventArg {
+    Unsigned(u64),
+    Signed(i64),
+    Float(f64),
+}
+
+impl EventArg {
+    /// Encode argument into the buffer
+    #[inline(always)]
+    pub fn encode(&self, buf: &mut Vec<u8>) {
+        let (kind, bits) = match *self {
+            Self::Unsigned(v) => (ARG_KIND_UNSIGNED, v),
+            Self::Signed(v) => (ARG_KIND_SIGNED, ((v << 1) ^ (v >> 63)) as u64),
+            Self::Float(v) => (ARG_KIND_FLOAT, v.to_bits()),
+        };
+        let len = if kind == ARG_KIND_FLOAT {
+            8
+        }
+        else {
+            ((64 + 7 - bits.leading_zeros()) >> 3) as u8
+        };
+        buf.push(kind << 4 | len);
+        buf.extend_from_slice(&bits.to_le_bytes()[..len as usize]);
+    }
+
+    /// Restore argument from its kind and raw value bytes. Returns `None` for unknown kind or value longer than 8 bytes
+    pub fn decode(kind: u8, bytes: &[u8]) -> Option<Self> {
+        if bytes.len() > 8 {
+            return None;
+        }
+        let mut raw = [0u8; 8];
+        raw[..bytes.len()].copy_from_slice(bytes);
+        let bits = u64::from_le_bytes(raw);
+        match kind {
+            ARG_KIND_UNSIGNED => Some(Self::Unsigned(bits)),
+            ARG_KIND_SIGNED => Some(Self::Signed(((bits >> 1) as i64) ^ -((bits & 1) as i64))),
+            ARG_KIND_FLOAT => Some(Self::Float(f64::from_bits(bits))),
+            _ => None
+        }
+    }
+}
+
+/// Encode the whole list of arguments, including arguments count. Panics if there are more than `MAX_ARGS` arguments
+#[inline(always)]
+pub fn encode_args(buf: &mut Vec<u8>, args: &[EventArg]) {
+    assert!(args.len() <= MAX_ARGS, "Too many event arguments: {}", args.len());
+    buf.push(args.len() as u8);
+    for arg in args {
+        arg.encode(buf);
+    }
+}
+
+macro_rules! impl_from_unsigned {
+    ($($t:ty),*) => {
+        $(impl From<$t> for EventArg {
+            fn from(v: $t) -> Self {
+                Self::Unsigned(v as u64)
+            }
+        })*
+    };
+}
+macro_rules! impl_from_signed {
+    ($($t:ty),*) => {
+        $(impl From<$t> for EventArg {
+            fn from(v: $t) -> Self {
+                Self::Signed(v as i64)
+            }
+        })*
+    };
+}
+
+impl_from_unsigned!(u8, u16, u32, u64, usize);
+impl_from_signed!(i8, i16, i32, i64, isize);
+
+impl From<bool> for EventArg {
+    fn from(v: bool) -> Self {
+        Self::Unsigned(v as u64)
+    }
+}
+impl From<f32> for EventArg {
+    fn from(v: f32) -> Self {
+        Self::Float(v as f64)
+    }
+}
+impl From<f64> for EventArg {
+    fn from(v: f64) -> Self {
+        Self::Float(v)
+    }
+}
@@ -1,10 +1,9 @@
 //! Simple hash map, aimed for better insertion performance.
-//! Memory overhead: 5*elements_cnt
-//!
-//! 256 id variants => 1280 bytes
+//! Memory overhead: at most 24*elements_cnt, at least 3072 bytes after the first insertion
 //!
 //! get overhead ~1ns
 
+use alloc::borrow::Cow;
 use alloc::string::{String, ToString};
 use alloc::vec::Vec;
 use serde::{Deserialize, Serialize};
@@ -13,7 +12,21 @@ use serde::{Deserialize, Serialize};
 pub enum EventType {
     Instant,
     RangeStart,
-    RangeEnd(u8),
+    RangeEnd(u32),
+    /// Instant event with a single argument, which is a new value of the counter
+    Counter,
+    /// Start of the range, which can be finished on another thread. Linked with the end by global range id
+    GlobalRangeStart,
+    /// End of the global range. Empty name means unnamed end
+    GlobalRangeEnd,
+    /// Instant event, which starts a new flow. Linked with other flow events by flow id
+    FlowBegin,
+    /// Instant event in the middle of the flow
+    FlowStep,
+    /// Instant event, which terminates the flow
+    FlowEnd,
+    /// Frame boundary with a single argument, which is the index of the new frame
+    Frame,
 }
 
 impl EventType {
@@ -22,80 +35,205 @@ impl EventType {
             Self::Instant => 0,
             Self::RangeStart => 1,
             Self::RangeEnd(_) => 2,
+            Self::Counter => 3,
+            Self::GlobalRangeStart => 4,
+            Self::GlobalRangeEnd => 5,
+            Self::FlowBegin => 6,
+            Self::FlowStep => 7,
+            Self::FlowEnd => 8,
+            Self::Frame => 9,
         }
     }
 }
 
-#[derive(Clone)]
-struct U32U8Map {
-    keys: [Option<u32>; 256],
-    values: [Option<u8>; 256],
-}
+/// Initial amount of slots, allocated on the first insertion
+const MAP_INITIAL_CAPACITY: usize = 256;
 
-impl Default for U32U8Map {
-    fn default() -> Self {
-        Self {
-            keys: [None; 256],
-            values: [None; 256],
-        }
-    }
+/// Open addressing hash map with linear probing. Table is kept at most half full and doubles its size when needed
+#[derive(Clone, Default)]
+struct U32U32Map {
+    /// (key, value) slots, amount is zero or a power of two
+    slots: Vec<Option<(u32, u32)>>,
+    len: usize,
 }
 
-impl U32U8Map {
+impl U32U32Map {
     const fn new() -> Self {
         Self {
-            keys: [None; 256],
-            values: [None; 256],
+            slots: Vec::new(),
+            len: 0,
         }
     }
 
     fn hash(&self, key: u32) -> usize {
-        (core::num::Wrapping(key).0 as usize).wrapping_mul(2654435761) % 256
+        (key as usize).wrapping_mul(2654435761) & (self.slots.len() - 1)
     }
 
-    fn insert(&mut self, key: u32, value: u8) -> Result<(), &'static str> {
+    fn insert(&mut self, key: u32, value: u32) {
+        if (self.len + 1) * 2 > self.slots.len() {
+            self.grow();
+        }
         let mut idx = self.hash(key);
-        for _ in 0..256 {
-            if self.keys[idx].is_none() || self.keys[idx] == Some(key) {
-                self.keys[idx] = Some(key);
-                self.values[idx] = Some(value);
-                return Ok(());
+        loop {
+            match self.slots[idx] {
+                Some((existing_key, _)) if existing_key != key => {
+                    idx = (idx + 1) & (self.slots.len() - 1);
+                }
+                Some(_) => {
+                    self.slots[idx] = Some((key, value));
+                    return;
+                }
+                None => {
+                    self.slots[idx] = Some((key, value));
+                    self.len += 1;
+                    return;
+                }
             }
-            idx = (idx + 1) % 256;
         }
-        Err("Map is full")
     }
 
-    fn get(&self, key: u32) -> Option<u8> {
+    fn get(&self, key: u32) -> Option<u32> {
+        if self.slots.is_empty() {
+            return None;
+        }
         let mut idx = self.hash(key);
-        for _ in 0..256 {
-            if let Some(existing_key) = self.keys[idx] {
-                if existing_key == key {
-                    return self.values[idx];
-                }
-            } else {
-                return None;
+        // Table always has free slots, so the loop is finite
+        while let Some((existing_key, value)) = self.slots[idx] {
+            if existing_key == key {
+                return Some(value);
             }
-            idx = (idx as u8).wrapping_add(1) as usize;
+            idx = (idx + 1) & (self.slots.len() - 1);
         }
         None
     }
+
+    fn grow(&mut self) {
+        let new_capacity = (self.slots.len() * 2).max(MAP_INITIAL_CAPACITY);
+        let old_slots = core::mem::replace(&mut self.slots, alloc::vec![None; new_capacity]);
+        self.len = 0;
+        for (key, value) in old_slots.into_iter().flatten() {
+            self.insert(key, value);
+        }
+    }
+}
+
+/// Identity of the static event metadata, which is cheap to compare.
+/// Static metadata of the same call site always has the same fingerprint. Addresses of the runtime strings are reused
+/// after they are freed, so non-static metadata has no fingerprint
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct MetaFingerprint {
+    name: (*const u8, usize),
+    arg_names: (*const &'static str, usize),
+    line: u32,
+}
+
+impl MetaFingerprint {
+    #[inline(always)]
+    fn new(meta: &EventMeta) -> Self {
+        Self {
+            name: (meta.name.as_ptr(), meta.name.len()),
+            arg_names: (meta.arg_names.as_ptr().cast(), meta.arg_names.len()),
+            line: meta.location.as_ref().map_or(0, |location| location.line),
+        }
+    }
 }
 
 /// ID to String mapping. Used to encode string into ID
+///
+/// Different events may have the same 32-bit hash. Event metadata is checked on lookup,
+/// and the colliding event is stored under the next key, so it gets its own ID.
 #[derive(Clone, Default)]
 pub struct IdMappingState {
     /// Used internally for faster lookup
-    id_map: U32U8Map,
-    last_id: u8,
+    id_map: U32U32Map,
+    last_id: u32,
+    /// Fingerprint of the last static metadata, matched with each ID. Used to skip full comparison of the metadata
+    fingerprints: Vec<Option<MetaFingerprint>>,
 
     tags_store: IdMapping,
 }
 
+// SAFETY: fingerprint pointers are only compared and never dereferenced
+unsafe impl Send for IdMappingState {}
+unsafe impl Sync for IdMappingState {}
+
+/// Place in the source code where the event is recorded
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SourceLocation {
+    pub module_path: Cow<'static, str>,
+    pub file: Cow<'static, str>,
+    pub line: u32,
+}
+
+impl SourceLocation {
+    pub const fn new(module_path: &'static str, file: &'static str, line: u32) -> Self {
+        Self {
+            module_path: Cow::Borrowed(module_path),
+            file: Cow::Borrowed(file),
+            line,
+        }
+    }
+}
+
+/// Information about the event, provided at the call site.
+/// Used to fill the event tag when the event is recorded for the first time.
+#[derive(Clone, Debug)]
+pub struct EventMeta<'a> {
+    pub name: &'a str,
+    /// Names of the numeric arguments, attached to the event
+    pub arg_names: &'a [&'a str],
+    pub location: Option<SourceLocation>,
+    /// Category, which can be enabled or disabled at runtime
+    pub category: Option<&'a str>,
+    /// Metadata and its strings are never freed, so their addresses identify the event.
+    /// Set for the metadata in statics. Otherwise, metadata is fully compared with the tag on each lookup
+    pub is_static: bool,
+}
+
+impl<'a> EventMeta<'a> {
+    /// Event with name only
+    pub const fn new(name: &'a str) -> Self {
+        Self {
+            name,
+            arg_names: &[],
+            location: None,
+            category: None,
+            is_static: false,
+        }
+    }
+}
+
+/// Information about a single event ID
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct EventTag {
+    pub name: String,
+    pub event_type: EventType,
+    /// Names of the numeric arguments, attached to the event
+    pub arg_names: Vec<String>,
+    pub location: Option<SourceLocation>,
+    pub category: Option<String>,
+}
+
+impl EventTag {
+    /// Check if the tag was created from the same event metadata and type
+    fn matches(&self, meta: &EventMeta, event_type: &EventType) -> bool {
+        let same_location = match (&self.location, &meta.location) {
+            (Some(a), Some(b)) => a.line == b.line && a.file == b.file && a.module_path == b.module_path,
+            (None, None) => true,
+            _ => false,
+        };
+        self.event_type.get_offs() == event_type.get_offs()
+            && self.name == meta.name
+            && self.arg_names.iter().map(String::as_str).eq(meta.arg_names.iter().copied())
+            && self.category.as_deref() == meta.category
+            && same_location
+    }
+}
+
 /// ID to String mapping. Used to decode events
 #[derive(Serialize, Deserialize, Clone, Debug, Default)]
 pub struct IdMapping {
-    pub tags: Vec<(String, EventType)>,
+    pub tags: Vec<EventTag>,
 }
 impl IdMapping {
     /// Create a new empty mapping
@@ -115,28 +253,108 @@ impl IdMappingState {
     /// Create a new empty mapping
     pub const fn new() -> Self {
         Self {
-            id_map: U32U8Map::new(),
+            id_map: U32U32Map::new(),
             last_id: 0,
+            fingerprints: Vec::new(),
             tags_store: IdMapping::new(),
         }
     }
 
+    /// Tags of all inserted events, indexed by ID
+    pub fn mapping(&self) -> &IdMapping {
+        &self.tags_store
+    }
+
     /// Lookup ID for the provided hash, or insert tag and acquire a new ID
     #[inline(always)]
-    pub fn insert_and_get_id(&mut self, hash: u32, tag: &str, event_type: EventType) -> u8 {
-        let offs = event_type.get_offs();
-        let hash = hash + offs;
-        match self.id_map.get(hash) {
-            Some(v) => {
-                v
-            },
-            None => {
-                let last_id = self.last_id;
-                self.last_id += 1;
-                self.id_map.insert(hash, last_id).unwrap();
-                self.tags_store.tags.push((tag.to_string(), event_type));
-                last_id
+    pub fn insert_and_get_id(&mut self, hash: u32, meta: &EventMeta, event_type: EventType) -> u32 {
+        // Event type is mixed into the key, so types of the same event don't take neighbouring keys
+        let mut key = hash ^ event_type.get_offs().wrapping_mul(0x9E37_79B9);
+        let fingerprint = meta.is_static.then(|| MetaFingerprint::new(meta));
+        loop {
+            let Some(id) = self.id_map.get(key) else {
+                return self.insert(key, fingerprint, meta, event_type);
+            };
+            if fingerprint.is_some() && self.fingerprints[id as usize] == fingerprint {
+                return id;
+            }
+            if self.tags_store.tags[id as usize].matches(meta, &event_type) {
+                if fingerprint.is_some() {
+                    self.fingerprints[id as usize] = fingerprint;
+                }
+                return id;
             }
+            // Hash collision with another event
+            key = next_key(key);
         }
     }
+
+    #[cold]
+    fn insert(&mut self, key: u32, fingerprint: Option<MetaFingerprint>, meta: &EventMeta, event_type: EventType) -> u32 {
+        let last_id = self.last_id;
+        self.last_id += 1;
+        self.id_map.insert(key, last_id);
+        self.fingerprints.push(fingerprint);
+        self.tags_store.tags.push(EventTag {
+            name: meta.name.to_string(),
+            event_type,
+            arg_names: meta.arg_names.iter().map(|s| s.to_string()).collect(),
+            location: meta.location.clone(),
+            category: meta.category.map(|s| s.to_string()),
+        });
+        last_id
+    }
+}
+
+/// Key for the event, which collided with another one on the current key
+fn next_key(key: u32) -> u32 {
+    (key ^ 0x5bd1_e995).wrapping_mul(0x0100_0193).rotate_left(13)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn colliding_hashes_get_separate_ids() {
+        let mut state = IdMappingState::new();
+        let a = EventMeta::new("a");
+        let b = EventMeta::new("b");
+
+        assert_eq!(state.insert_and_get_id(1, &a, EventType::Instant), 0);
+        assert_eq!(state.insert_and_get_id(1, &b, EventType::Instant), 1);
+        assert_eq!(state.insert_and_get_id(1, &a, EventType::Instant), 0);
+        assert_eq!(state.insert_and_get_id(1, &b, EventType::Instant), 1);
+
+        // The same name, known only at runtime
+        let name = "b".to_string();
+        assert_eq!(state.insert_and_get_id(1, &EventMeta::new(&name), EventType::Instant), 1);
+
+        // Different types of the same event, and their neighbour hash
+        assert_eq!(state.insert_and_get_id(2, &a, EventType::RangeStart), 2);
+        assert_eq!(state.insert_and_get_id(2, &b, EventType::Instant), 3);
+        assert_eq!(state.insert_and_get_id(3, &a, EventType::Instant), 4);
+
+        let tags = IdMapping::from(state).tags;
+        let names: Vec<_> = tags.iter().map(|tag| tag.name.as_str()).collect();
+        assert_eq!(names, ["a", "b", "a", "b", "a"]);
+    }
+
+    #[test]
+    fn dynamic_names_at_the_same_address_get_separate_ids() {
+        let mut state = IdMappingState::new();
+        let mut name = "a".to_string();
+        let addr = name.as_ptr();
+        assert_eq!(state.insert_and_get_id(1, &EventMeta::new(&name), EventType::Instant), 0);
+
+        // Colliding name of the same length, stored at the same address
+        name.clear();
+        name.push('b');
+        assert_eq!(name.as_ptr(), addr);
+        assert_eq!(state.insert_and_get_id(1, &EventMeta::new(&name), EventType::Instant), 1);
+
+        name.clear();
+        name.push('a');
+        assert_eq!(state.insert_and_get_id(1, &EventMeta::new(&name), EventType::Instant), 0);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,51 @@
+//! Identifiers, which link events recorded on different threads.
+//!
+//! Encoded format: `[kind << 4 | len] [len bytes of LE value]`, placed right after the event timestamp.
+
+use alloc::vec::Vec;
+
+pub const LINK_KIND_TASK: u8 = 0;
+pub const LINK_KIND_GLOBAL_RANGE: u8 = 1;
+pub const LINK_KIND_FLOW: u8 = 2;
+
+/// Process-wide identifier, attached to an event
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum EventLink {
+    /// Event belongs to the async task with given id
+    Task(u64),
+    /// Event is a start or an end of the global range with given id
+    GlobalRange(u64),
+    /// Event is a part of the flow with given id
+    Flow(u64),
+}
+
+impl EventLink {
+    /// Encode link into the buffer
+    #[inline(always)]
+    pub fn encode(&self, buf: &mut Vec<u8>) {
+        let (kind, id) = match *self {
+            Self::Task(id) => (LINK_KIND_TASK, id),
+            Self::GlobalRange(id) => (LINK_KIND_GLOBAL_RANGE, id),
+            Self::Flow(id) => (LINK_KIND_FLOW, id),
+        };
+        let len = ((64 + 7 - id.leading_zeros()) >> 3) as u8;
+        buf.push(kind << 4 | len);
+        buf.extend_from_slice(&id.to_le_bytes()[..len as usize]);
+    }
+
+    /// Restore link from its kind and raw id bytes. Returns `None` for unknown kind or id longer than 8 bytes
+    pub fn decode(kind: u8, bytes: &[u8]) -> Option<Self> {
+        if bytes.len() > 8 {
+            return None;
+        }
+        let mut raw = [0u8; 8];
+        raw[..bytes.len()].copy_from_slice(bytes);
+        let id = u64::from_le_bytes(raw);
+        match kind {
+            LINK_KIND_TASK => Some(Self::Task(id)),
+            LINK_KIND_GLOBAL_RANGE => Some(Self::GlobalRange(id)),
+            LINK_KIND_FLOW => Some(Self::Flow(id)),
+            _ => None
+        }
+    }
+}
@@ -0,0 +1,19 @@
+//! Text message, attached to instant events.
+//!
+//! Encoded format: `[len: u16 LE] [len bytes of UTF-8]`, placed after the event arguments.
+
+use alloc::vec::Vec;
+
+/// Longer messages are truncated
+pub const MAX_MESSAGE_LEN: usize = u16::MAX as usize;
+
+/// Encode message into the buffer, truncating it to `MAX_MESSAGE_LEN` bytes on a char boundary
+#[inline(always)]
+pub fn encode_message(buf: &mut Vec<u8>, message: &str) {
+    let mut len = message.len().min(MAX_MESSAGE_LEN);
+    while !message.is_char_boundary(len) {
+        len -= 1;
+    }
+    buf.extend_from_slice(&(len as u16).to_le_bytes());
+    buf.extend_from_slice(&message.as_bytes()[..len]);
+}
@@ -2,19 +2,29 @@ use alloc::string::String;
 use alloc::vec::Vec;
 use core::marker::PhantomData;
 use core::sync::atomic::{AtomicUsize, Ordering};
-use crate::config::LocalStorageConfig;
+use crate::config::{BuffersLimitPolicy, LocalStorageConfig};
 use crate::headers::{LocalPacketHeader, ThreadInfo};
-use crate::local_storage::id_mapping::{EventType, IdMappingState};
+use crate::local_storage::args::{encode_args, EventArg};
+use crate::local_storage::id_mapping::{EventMeta, EventType, IdMappingState};
+use crate::local_storage::links::EventLink;
+use crate::local_storage::message::encode_message;
+use crate::local_storage::varint::encode_varint;
 use crate::Timestamp;
 
 use crate::timestamp::TimestampProvider;
 
 pub mod id_mapping;
+pub mod args;
+pub mod links;
+pub mod message;
+pub mod varint;
 
 pub trait GlobalStorageImpl {
     fn flush(&self, header: &LocalPacketHeader, data: &[u8]);
     fn try_flush(&self, header: &LocalPacketHeader, data: &[u8]) -> bool;
     fn is_buf_available(&self) -> bool;
+    /// Report events, dropped from the local buffer. Header contains only thread id and time range of the lost events
+    fn report_lost(&self, header: &LocalPacketHeader);
 }
 
 pub struct LocalStorage<G: GlobalStorageImpl> {
@@ -23,15 +33,28 @@ pub struct LocalStorage<G: GlobalStorageImpl> {
     prev_tm: u64,
 
     buf: Vec<u8>,
+    /// Capacity of `buf`, included in `TOTAL_BUFFERS_CAPACITY`
+    accounted_capacity: usize,
     id_store: IdMappingState,
 
     local_packet_header: LocalPacketHeader,
 
     global_storage_ref: G,
-    last_range_ord_id: u8
+    last_range_ord_id: u32,
+    /// Ord ids of the finished ranges. They are reused first, so encoded ids stay short
+    free_range_ord_ids: Vec<u32>,
+    /// With `Drop` policy, ord ids of the finished ranges, which end is not flushed yet. If the end is dropped, the id is never reused
+    unflushed_free_range_ord_ids: Vec<u32>,
 }
 
 static CUR_THREAD_ID: AtomicUsize = AtomicUsize::new(1);
+/// Total capacity of all thread-local buffers in bytes, checked against `LocalStorageConfig::total_buffers_limit`
+static TOTAL_BUFFERS_CAPACITY: AtomicUsize = AtomicUsize::new(0);
+
+/// Forget capacity of all existing thread-local buffers. Used in the forked child, where buffers of other threads are unreachable
+pub fn reset_total_buffers_capacity() {
+    TOTAL_BUFFERS_CAPACITY.store(0, Ordering::Relaxed);
+}
 
 impl<G: GlobalStorageImpl> LocalStorage<G> {
     pub fn new(global_storage_ref: G, thread_info: Option<ThreadInfo>, config: LocalStorageConfig)-> Self {
@@ -40,6 +63,7 @@ impl<G: GlobalStorageImpl> LocalStorage<G> {
         LocalStorage {
             config,
             buf: Vec::new(),
+            accounted_capacity: 0,
             prev_tm: 0,
 
             id_store: Default::default(),
@@ -51,22 +75,45 @@ impl<G: GlobalStorageImpl> LocalStorage<G> {
             },
 
             global_storage_ref,
-            last_range_ord_id: 0
+            last_range_ord_id: 0,
+            free_range_ord_ids: Vec::new(),
+            unflushed_free_range_ord_ids: Vec::new(),
         }
     }
 
-    fn new_range_ord_id(&mut self) -> u8 {
+    /// Range ord id is unique among the ranges, which are not finished yet
+    fn new_range_ord_id(&mut self) -> u32 {
+        if let Some(range_ord_id) = self.free_range_ord_ids.pop() {
+            return range_ord_id;
+        }
         let range_ord_id = self.last_range_ord_id;
-        self.last_range_ord_id = self.last_range_ord_id.wrapping_add(1);
+        self.last_range_ord_id += 1;
         range_ord_id
     }
 
     #[inline(always)]
-    pub fn event_range_start(&mut self, hash: u32, name: &str) -> RangeStartRepr {
+    pub fn event_range_start(&mut self, hash: u32, meta: &EventMeta) -> RangeStartRepr {
+        self.event_range_start_with_args(hash, meta, &[])
+    }
+
+    /// Start range event with attached numeric arguments. `meta.arg_names` must have the same length as `args`
+    #[inline(always)]
+    pub fn event_range_start_with_args(&mut self, hash: u32, meta: &EventMeta, args: &[EventArg]) -> RangeStartRepr {
+        self.range_start(hash, meta, args, None)
+    }
+
+    /// Start range event, linked with other events by process-wide identifier
+    #[inline(always)]
+    pub fn event_range_start_with_link(&mut self, hash: u32, meta: &EventMeta, args: &[EventArg], link: EventLink) -> RangeStartRepr {
+        self.range_start(hash, meta, args, Some(link))
+    }
+
+    #[inline(always)]
+    fn range_start(&mut self, hash: u32, meta: &EventMeta, args: &[EventArg], link: Option<EventLink>) -> RangeStartRepr {
         // On a new range event we acquire new range_ord_id to match start and end events
         let range_ord_id = self.new_range_ord_id();
-        let start_id = self.id_store.insert_and_get_id(hash, name, EventType::RangeStart);
-        self.range_event(Some(start_id), range_ord_id);
+        let start_id = self.id_store.insert_and_get_id(hash, meta, EventType::RangeStart);
+        self.range_event(Some(start_id), range_ord_id, args, link);
 
         RangeStartRepr {
             range_ord_id,
@@ -77,20 +124,26 @@ impl<G: GlobalStorageImpl> LocalStorage<G> {
     }
 
     #[inline(always)]
-    pub fn event_range_end(&mut self, range_start: RangeStartRepr, hash: u32, name: &str) {
+    pub fn event_range_end(&mut self, range_start: RangeStartRepr, hash: u32, meta: &EventMeta) {
         let range_ord_id = range_start.range_ord_id;
         let start_id = range_start.range_start_id;
         if hash != 0 {
-            let end_id = self.id_store.insert_and_get_id(hash, name, EventType::RangeEnd(start_id));
-            self.range_event(Some(end_id), range_ord_id);
+            let end_id = self.id_store.insert_and_get_id(hash, meta, EventType::RangeEnd(start_id));
+            self.range_event(Some(end_id), range_ord_id, &[], None);
         }
         else {
-            self.range_event(None, range_ord_id);
+            self.range_event(None, range_ord_id, &[], None);
+        }
+        if self.config.buffers_limit_policy == BuffersLimitPolicy::Drop {
+            self.unflushed_free_range_ord_ids.push(range_ord_id);
+        }
+        else {
+            self.free_range_ord_ids.push(range_ord_id);
         }
     }
 
     #[inline(always)]
-    fn range_event(&mut self, id: Option<u8>, range_ord_id: u8) {
+    fn range_event(&mut self, id: Option<u32>, range_ord_id: u32, args: &[EventArg], link: Option<EventLink>) {
         //      STAGE 2: Acquire timestamp and calculate now, dif_tm
         //    (3ns on non-serializing x86 timestamp, 11ns on serializing x86 timestamp)
         let timestamp = Timestamp::now();
@@ -101,12 +154,22 @@ impl<G: GlobalStorageImpl> LocalStorage<G> {
         //      STAGE 4: PUSH VALUES
         let dif_tm_bytes: [u8; 8] = dif_tm.to_le_bytes();
         let dif_tm_bytes_len = ((Timestamp::TIMESTAMP_VALID_BITS as u32 + 7 - dif_tm.leading_zeros()) >> 3) as u8;
-        let buf = match id {
-            Some(id) => [id, dif_tm_bytes_len | 0x80, range_ord_id],
-            None => [0, dif_tm_bytes_len | 0xC0, range_ord_id]
+        let args_flag = if args.is_empty() { 0 } else { 0x20 };
+        let link_flag = if link.is_none() { 0 } else { 0x10 };
+        let flags = match id {
+            Some(_) => dif_tm_bytes_len | args_flag | link_flag | 0x80,
+            None => dif_tm_bytes_len | args_flag | link_flag | 0xC0,
         };
-        self.buf.extend_from_slice(&buf);
+        encode_varint(&mut self.buf, id.unwrap_or(0));
+        self.buf.push(flags);
+        encode_varint(&mut self.buf, range_ord_id);
         self.buf.extend_from_slice(&dif_tm_bytes[..dif_tm_bytes_len as usize]);
+        if let Some(link) = link {
+            link.encode(&mut self.buf);
+        }
+        if !args.is_empty() {
+            encode_args(&mut self.buf, args);
+        }
 
 
         //      STAGE 5: flushing
@@ -115,14 +178,64 @@ impl<G: GlobalStorageImpl> LocalStorage<G> {
 
 
     #[inline(always)]
-    pub fn event_instant(&mut self, hash: u32, string: &str) {
+    pub fn event_instant(&mut self, hash: u32, meta: &EventMeta) {
+        //      STAGE 1: insert string and get ID.
+        let id = self.id_store.insert_and_get_id(hash, meta, EventType::Instant);
+        self.event(id, &[], None, None);
+    }
+
+    /// Instant event with attached numeric arguments. `meta.arg_names` must have the same length as `args`
+    #[inline(always)]
+    pub fn event_instant_with_args(&mut self, hash: u32, meta: &EventMeta, args: &[EventArg]) {
         //      STAGE 1: insert string and get ID.
-        let id = self.id_store.insert_and_get_id(hash, string, EventType::Instant);
-        self.event(id);
+        let id = self.id_store.insert_and_get_id(hash, meta, EventType::Instant);
+        self.event(id, args, None, None);
+    }
+
+    /// Instant event with attached text message. Message is truncated to `message::MAX_MESSAGE_LEN` bytes
+    #[inline(always)]
+    pub fn event_instant_with_message(&mut self, hash: u32, meta: &EventMeta, args: &[EventArg], message: &str) {
+        let id = self.id_store.insert_and_get_id(hash, meta, EventType::Instant);
+        self.event(id, args, None, Some(message));
     }
 
+    /// Record a new value of the counter
     #[inline(always)]
-    fn event(&mut self, id: u8) {
+    pub fn event_counter(&mut self, hash: u32, meta: &EventMeta, value: EventArg) {
+        let id = self.id_store.insert_and_get_id(hash, meta, EventType::Counter);
+        self.event(id, &[value], None, None);
+    }
+
+    /// Record the start of a new frame with the given index
+    #[inline(always)]
+    pub fn event_frame(&mut self, hash: u32, meta: &EventMeta, frame_index: u64) {
+        let id = self.id_store.insert_and_get_id(hash, meta, EventType::Frame);
+        self.event(id, &[EventArg::Unsigned(frame_index)], None, None);
+    }
+
+    /// Start global range, which can be finished on any thread. `range_id` must be unique within the process
+    #[inline(always)]
+    pub fn event_global_range_start(&mut self, hash: u32, meta: &EventMeta, args: &[EventArg], range_id: u64) {
+        let id = self.id_store.insert_and_get_id(hash, meta, EventType::GlobalRangeStart);
+        self.event(id, args, Some(EventLink::GlobalRange(range_id)), None);
+    }
+
+    /// Finish global range. Empty name in `meta` means unnamed end
+    #[inline(always)]
+    pub fn event_global_range_end(&mut self, hash: u32, meta: &EventMeta, range_id: u64) {
+        let id = self.id_store.insert_and_get_id(hash, meta, EventType::GlobalRangeEnd);
+        self.event(id, &[], Some(EventLink::GlobalRange(range_id)), None);
+    }
+
+    /// Flow event: `event_type` must be one of `FlowBegin`, `FlowStep` or `FlowEnd`. `flow_id` must be unique within the process
+    #[inline(always)]
+    pub fn event_flow(&mut self, hash: u32, meta: &EventMeta, args: &[EventArg], event_type: EventType, flow_id: u64) {
+        let id = self.id_store.insert_and_get_id(hash, meta, event_type);
+        self.event(id, args, Some(EventLink::Flow(flow_id)), None);
+    }
+
+    #[inline(always)]
+    fn event(&mut self, id: u32, args: &[EventArg], link: Option<EventLink>, message: Option<&str>) {
         //      STAGE 2: Acquire timestamp and calculate now, dif_tm
         //    (3ns on non-serializing x86 timestamp, 11ns on serializing x86 timestamp)
         let timestamp = Timestamp::now();
@@ -133,9 +246,21 @@ impl<G: GlobalStorageImpl> LocalStorage<G> {
         //      STAGE 4: PUSH VALUES
         let dif_tm_bytes: [u8; 8] = dif_tm.to_le_bytes();
         let dif_tm_bytes_len = ((Timestamp::TIMESTAMP_VALID_BITS as u32 + 7 - dif_tm.leading_zeros()) >> 3) as u8;
-        let buf = [id, dif_tm_bytes_len];
-        self.buf.extend_from_slice(&buf);
+        let args_flag = if args.is_empty() { 0 } else { 0x20 };
+        let link_flag = if link.is_none() { 0 } else { 0x10 };
+        let message_flag = if message.is_none() { 0 } else { 0x40 };
+        encode_varint(&mut self.buf, id);
+        self.buf.push(dif_tm_bytes_len | args_flag | link_flag | message_flag);
         self.buf.extend_from_slice(&dif_tm_bytes[..dif_tm_bytes_len as usize]);
+        if let Some(link) = link {
+            link.encode(&mut self.buf);
+        }
+        if !args.is_empty() {
+            encode_args(&mut self.buf, args);
+        }
+        if let Some(message) = message {
+            encode_message(&mut self.buf, message);
+        }
 
 
         //      STAGE 5: flushing
@@ -162,6 +287,14 @@ impl<G: GlobalStorageImpl> LocalStorage<G> {
     /// Check buffer length, and flush if the buffer is full
     #[inline(always)]
     pub fn auto_flush(&mut self) {
+        if self.buf.capacity() != self.accounted_capacity {
+            let total = self.account_capacity();
+            if self.config.total_buffers_limit.is_some_and(|limit| total > limit) {
+                self.buffers_limit_reached();
+                return;
+            }
+        }
+
         if self.buf.len() >= self.config.flush_threshold {
             self.flush(true);
         }
@@ -170,6 +303,56 @@ impl<G: GlobalStorageImpl> LocalStorage<G> {
         }
     }
 
+    /// Update `TOTAL_BUFFERS_CAPACITY` with the current buffer capacity. Returns new total capacity
+    fn account_capacity(&mut self) -> usize {
+        let capacity = self.buf.capacity();
+        let total = if capacity > self.accounted_capacity {
+            let dif = capacity - self.accounted_capacity;
+            TOTAL_BUFFERS_CAPACITY.fetch_add(dif, Ordering::Relaxed) + dif
+        }
+        else {
+            let dif = self.accounted_capacity - capacity;
+            TOTAL_BUFFERS_CAPACITY.fetch_sub(dif, Ordering::Relaxed) - dif
+        };
+        self.accounted_capacity = capacity;
+        total
+    }
+
+    /// Apply configured policy and shrink the buffer, so other threads can use the memory
+    #[cold]
+    fn buffers_limit_reached(&mut self) {
+        match self.config.buffers_limit_policy {
+            BuffersLimitPolicy::Block => self.flush(true),
+            BuffersLimitPolicy::FlushEarly => self.flush(false),
+            BuffersLimitPolicy::Drop => self.drop_events(),
+        }
+        if self.buf.is_empty() {
+            self.buf.shrink_to(self.config.flush_attempt_threshold);
+            self.account_capacity();
+        }
+    }
+
+    /// Clear event buffer and report time range of the lost events to the global storage
+    fn drop_events(&mut self) {
+        if self.buf.is_empty() {
+            return;
+        }
+
+        let header = LocalPacketHeader {
+            thread_ord_id: self.local_packet_header.thread_ord_id,
+            start_timestamp: self.local_packet_header.start_timestamp,
+            end_timestamp: self.prev_tm,
+            ..Default::default()
+        };
+        self.global_storage_ref.report_lost(&header);
+
+        // Thread name is kept to be sent with the next packet
+        self.buf.clear();
+        // Parser still sees ranges with dropped ends as started, so their ord ids can't be reused
+        self.unflushed_free_range_ord_ids.clear();
+        self.local_packet_header.start_timestamp = 0;
+    }
+
     /// Flush whole event buffer data to the global storage
     pub fn flush(&mut self, forced: bool) {
         if self.buf.is_empty() {
@@ -179,7 +362,8 @@ impl<G: GlobalStorageImpl> LocalStorage<G> {
 
         // Fill header
         self.local_packet_header.end_timestamp = self.prev_tm;
-        self.local_packet_header.id_store = self.id_store.clone().into();
+        // Lookup state is not needed for decoding
+        self.local_packet_header.id_store = self.id_store.mapping().clone();
 
         let success = if forced {
             self.global_storage_ref.flush(&self.local_packet_header, &self.buf);
@@ -192,6 +376,7 @@ impl<G: GlobalStorageImpl> LocalStorage<G> {
         //cleanup
         if success {
             self.buf.clear();
+            self.free_range_ord_ids.append(&mut self.unflushed_free_range_ord_ids);
             if let Some(thread_info) = &mut self.local_packet_header.thread_info {
                 if thread_info.new_thread_name.is_some() {
                     thread_info.new_thread_name = None;
@@ -205,13 +390,14 @@ impl<G: GlobalStorageImpl> LocalStorage<G> {
 impl<G: GlobalStorageImpl> Drop for LocalStorage<G> {
     fn drop(&mut self) {
         self.flush(true);
+        TOTAL_BUFFERS_CAPACITY.fetch_sub(self.accounted_capacity, Ordering::Relaxed);
     }
 }
 
 #[derive(Copy, Clone)]
 pub struct RangeStartRepr {
-    range_start_id: u8, // required to create potentially new end event
-    range_ord_id: u8, // required to match with start event during parsing
+    range_start_id: u32, // required to create potentially new end event
+    range_ord_id: u32, // required to match with start event during parsing
 
     _not_send: PhantomData<*const ()>
 }
\ No newline at end of file
@@ -0,0 +1,16 @@
+//! Variable-length unsigned integers (LEB128), used for event IDs and range ord IDs.
+//!
+//! Encoded format: 7 bits of the value per byte, starting from the lowest bits. High bit marks that more bytes follow.
+//! Values below 128 take a single byte.
+
+use alloc::vec::Vec;
+
+/// Encode value into the buffer
+#[inline(always)]
+pub fn encode_varint(buf: &mut Vec<u8>, mut value: u32) {
+    while value >= 0x80 {
+        buf.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    buf.push(value as u8);
+}
@@ -24,11 +24,11 @@ pub trait ConfiguredSender: Sender + Sized {
 /// Storage for multiple senders, which is also a sender.
 #[derive(Default)]
 pub struct SenderChain {
-    senders: Vec<Box<dyn Sender>>,
+    senders: Vec<Box<dyn Sender + Send>>,
 }
 
 impl SenderChain {
-    pub fn with_sender<T: Sender + 'static>(&mut self, sender: T) {
+    pub fn with_sender<T: Sender + Send + 'static>(&mut self, sender: T) {
         self.senders.push(Box::new(sender))
     }
 }
@@ -1,25 +1,283 @@
 use proc_macro::TokenStream;
 use quote::quote;
-use syn::{LitStr, parse_macro_input, Expr};
+use proc_macro2::TokenStream as TokenStream2;
+use syn::{LitStr, parse_macro_input, Expr, ExprLit, Ident, ItemFn, Lit, Token};
 use syn::parse::{Parse, ParseStream};
 use syn::token::Comma;
 
+/// Expansions of the event macros with `off` feature: nothing is recorded, guards and flow ids are
+/// the disabled zero-sized types from `sparkles`
+///
+/// Event names and argument values are only borrowed in the unreachable branch, to keep them type-checked
+/// and avoid unused variable warnings.
+mod off {
+    use proc_macro::TokenStream;
+    use proc_macro2::TokenStream as TokenStream2;
+    use quote::quote;
+    use syn::{Expr, Ident, ItemFn};
+
+    fn used(exprs: &[&Expr]) -> TokenStream2 {
+        if exprs.is_empty() {
+            return quote! {};
+        }
+        quote! {
+            if false {
+                let _ = (#(&(#exprs),)*);
+            }
+        }
+    }
+
+    /// Instant events and counters
+    pub fn unit(exprs: &[&Expr]) -> TokenStream {
+        let used = used(exprs);
+        TokenStream::from(quote! {
+            {
+                #used
+            }
+        })
+    }
+
+    /// Disabled range guard, zero-sized with `off` feature
+    pub fn guard(exprs: &[&Expr]) -> TokenStream {
+        let used = used(exprs);
+        TokenStream::from(quote! {
+            {
+                #used
+                ::sparkles::RangeStartGuard::disabled()
+            }
+        })
+    }
+
+    /// Disabled global range guard, zero-sized with `off` feature
+    pub fn global_guard(exprs: &[&Expr]) -> TokenStream {
+        let used = used(exprs);
+        TokenStream::from(quote! {
+            {
+                #used
+                ::sparkles::GlobalRangeGuard::disabled()
+            }
+        })
+    }
+
+    /// Disabled flow id, zero-sized with `off` feature
+    pub fn flow(exprs: &[&Expr]) -> TokenStream {
+        let used = used(exprs);
+        TokenStream::from(quote! {
+            {
+                #used
+                ::sparkles::FlowId::disabled()
+            }
+        })
+    }
+
+    /// Function with captured arguments
+    pub fn function(item: ItemFn, args: &[Ident]) -> TokenStream {
+        let ItemFn { attrs, vis, sig, block } = item;
+        if args.is_empty() {
+            return TokenStream::from(quote! { #(#attrs)* #vis #sig #block });
+        }
+        let stmts = &block.stmts;
+        TokenStream::from(quote! {
+            #(#attrs)* #vis #sig {
+                if false {
+                    let _ = (#(&#args,)*);
+                }
+                #(#stmts)*
+            }
+        })
+    }
+
+    /// Range ends and flow events
+    pub fn consume(guard: &Expr, exprs: &[&Expr]) -> TokenStream {
+        let used = used(exprs);
+        TokenStream::from(quote! {
+            {
+                #used
+                let _ = #guard;
+            }
+        })
+    }
+}
+
+/// Event name: string literal is hashed at compile time,
+/// any other expression is treated as a runtime name (anything that implements `AsRef<str>`)
+enum EventName {
+    Literal(LitStr),
+    Dynamic(Expr),
+}
+
+impl Parse for EventName {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        match input.parse()? {
+            Expr::Lit(ExprLit { lit: Lit::Str(name), .. }) => Ok(Self::Literal(name)),
+            name => Ok(Self::Dynamic(name)),
+        }
+    }
+}
+
+impl EventName {
+    /// Expression of type `&str` for the runtime name
+    fn dynamic_str(name: &Expr) -> TokenStream2 {
+        quote! {
+            ::core::convert::AsRef::<str>::as_ref(&(#name))
+        }
+    }
+}
+
+/// Event name, optionally followed by numeric arguments: `"name", arg1 = value1, arg2 = value2`
+///
+/// Name can be preceded by a category: `"category", "name"`
+struct EventInput {
+    category: Option<LitStr>,
+    name: EventName,
+    args: Vec<(Ident, Expr)>,
+}
+
+impl Parse for EventInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut category = None;
+        let mut name = input.parse()?;
+        if input.peek(Comma) && !Self::peek_args(input) {
+            let EventName::Literal(category_name) = name else {
+                return Err(input.error("Event category must be a string literal"));
+            };
+            input.parse::<Comma>()?;
+            category = Some(category_name);
+            name = input.parse()?;
+            if let EventName::Dynamic(name) = &name {
+                return Err(syn::Error::new_spanned(name, "Event categories are supported only for string literal event names"));
+            }
+        }
+        let mut args = Vec::new();
+        while !input.is_empty() {
+            input.parse::<Comma>()?;
+            if input.is_empty() {
+                break;
+            }
+            let arg_name: Ident = input.parse()?;
+            input.parse::<Token![=]>()?;
+            let value: Expr = input.parse()?;
+            args.push((arg_name, value));
+        }
+        Ok(Self {
+            category,
+            name,
+            args,
+        })
+    }
+}
+
+impl EventInput {
+    /// Check if the rest of the input is `, arg = value` list, not `, name` after the category
+    fn peek_args(input: ParseStream) -> bool {
+        let fork = input.fork();
+        fork.parse::<Comma>().is_ok()
+            && (fork.is_empty() || (fork.peek(Ident) && fork.peek2(Token![=]) && !fork.peek2(Token![==])))
+    }
+
+    /// Names and values of the event arguments
+    fn args(&self) -> (Vec<String>, Vec<&Expr>) {
+        self.args.iter().map(|(name, value)| (name.to_string(), value)).unzip()
+    }
+
+    fn dynamic_name_with_args_error(&self, name: &Expr) -> Option<TokenStream> {
+        if !self.args.is_empty() {
+            let err = syn::Error::new_spanned(name, "Event arguments are supported only for string literal event names");
+            Some(err.to_compile_error().into())
+        }
+        else {
+            None
+        }
+    }
+
+    /// Runtime name and argument values
+    fn exprs(&self) -> Vec<&Expr> {
+        let name = match &self.name {
+            EventName::Dynamic(name) => Some(name),
+            EventName::Literal(_) => None,
+        };
+        name.into_iter().chain(self.args.iter().map(|(_, value)| value)).collect()
+    }
+
+    /// Static event metadata and hash for the string literal name
+    fn event_meta(&self, name: &LitStr, arg_names: &[String]) -> (u32, TokenStream2) {
+        let category = self.category.as_ref().map(|category| category.value());
+        event_meta(&name.value(), arg_names, category.as_deref())
+    }
+
+    /// Wrap event expression into the runtime check of the category.
+    /// `disabled` is the value of the expression when the category is disabled, `None` for unit expressions
+    fn category_check(&self, expr: TokenStream2, disabled: Option<TokenStream2>) -> TokenStream2 {
+        let Some(category) = &self.category else {
+            return expr;
+        };
+        let disabled = disabled.map(|disabled| quote! {
+            else {
+                #disabled
+            }
+        });
+        quote! {
+            {
+                static CATEGORY: sparkles::CategoryCallsite = sparkles::CategoryCallsite::new(#category);
+                if CATEGORY.is_enabled() {
+                    #expr
+                }
+                #disabled
+            }
+        }
+    }
+}
+
 /// Create instant event with given name
+///
+/// Numeric values can be attached to the event as `name = value` pairs.
+/// Any integer, float or bool value is accepted.
+///
+/// If the name is not a string literal, it is treated as a runtime name and hashed on each call.
+///
+/// Optional category can be specified before the string literal name. Categories can be enabled and disabled at runtime
+/// with `sparkles::enable_category` and `sparkles::disable_category`. The same is supported by `range_event_start!`,
+/// `global_range_start!` and flow macros.
 /// # Example
-/// ```rust
+/// ```rust,ignore
 /// sparkles_macro::instant_event!("Packet received");
+/// sparkles_macro::instant_event!("net", "Packet received");
+/// sparkles_macro::instant_event!("Packet received", size = packet.len(), queue_depth = queue.len());
+/// sparkles_macro::instant_event!(format!("Connection #{} closed", conn_id));
 /// ```
 #[proc_macro]
 pub fn instant_event(input: TokenStream) -> TokenStream {
-    let input = parse_macro_input!(input as LitStr);
-    let s = input.value();
-    let hash = calculate_hash(&s);
+    let input = parse_macro_input!(input as EventInput);
+    if cfg!(feature = "off") {
+        return off::unit(&input.exprs());
+    }
 
-    let expanded = quote! {
-        sparkles::instant_event(#hash, #s)
+    let expanded = match &input.name {
+        EventName::Literal(name) if input.args.is_empty() => {
+            let (hash, meta) = input.event_meta(name, &[]);
+            quote! {
+                sparkles::instant_event(#hash, #meta)
+            }
+        }
+        EventName::Literal(name) => {
+            let (arg_names, arg_values) = input.args();
+            let (hash, meta) = input.event_meta(name, &arg_names);
+            quote! {
+                sparkles::instant_event_with_args(#hash, #meta, &[#(sparkles::EventArg::from(#arg_values)),*])
+            }
+        }
+        EventName::Dynamic(name) => {
+            if let Some(err) = input.dynamic_name_with_args_error(name) {
+                return err;
+            }
+            let name = EventName::dynamic_str(name);
+            quote! {
+                sparkles::instant_event_dyn(#name)
+            }
+        }
     };
 
-    TokenStream::from(expanded)
+    TokenStream::from(input.category_check(expanded, None))
 }
 
 /// Create range event with given name
@@ -27,10 +285,14 @@ pub fn instant_event(input: TokenStream) -> TokenStream {
 /// Can be finished with two options:
 /// 1. Drop the guard
 /// 2. Call `sparkles_macro::range_event_end!(guard, "name")`
+///
+/// Numeric values can be attached to the range start as `name = value` pairs, same as for `instant_event!`.
+///
+/// If the name is not a string literal, it is treated as a runtime name and hashed on each call.
 /// 
 /// # Example
-/// ```rust
-/// let packet_proc = sparkles_macro::range_event_start!("Packet parsing");
+/// ```rust,ignore
+/// let packet_proc = sparkles_macro::range_event_start!("Packet parsing", size = packet.len());
 /// // Do some work
 /// let Ok(data) = parse_packet(&packet) else {
 ///    sparkles_macro::range_event_end!(packet_proc, "Failed");
@@ -39,21 +301,197 @@ pub fn instant_event(input: TokenStream) -> TokenStream {
 /// ```
 #[proc_macro]
 pub fn range_event_start(input: TokenStream) -> TokenStream {
-    let input = parse_macro_input!(input as LitStr);
-    let s = input.value();
-    let hash = calculate_hash(&s);
+    let input = parse_macro_input!(input as EventInput);
+    if cfg!(feature = "off") {
+        return off::guard(&input.exprs());
+    }
+
+    let expanded = match &input.name {
+        EventName::Literal(name) if input.args.is_empty() => {
+            let (hash, meta) = input.event_meta(name, &[]);
+            quote! {
+                sparkles::range_event_start(#hash, #meta)
+            }
+        }
+        EventName::Literal(name) => {
+            let (arg_names, arg_values) = input.args();
+            let (hash, meta) = input.event_meta(name, &arg_names);
+            quote! {
+                sparkles::range_event_start_with_args(#hash, #meta, &[#(sparkles::EventArg::from(#arg_values)),*])
+            }
+        }
+        EventName::Dynamic(name) => {
+            if let Some(err) = input.dynamic_name_with_args_error(name) {
+                return err;
+            }
+            let name = EventName::dynamic_str(name);
+            quote! {
+                sparkles::range_event_start_dyn(#name)
+            }
+        }
+    };
+
+    TokenStream::from(input.category_check(expanded, Some(quote! { sparkles::RangeStartGuard::disabled() })))
+}
+
+/// Start range, which can be finished on any thread
+///
+/// Returns `Send` guard, which can be moved to another thread and finished with `range_event_end!`, or dropped.
+/// Numeric arguments and runtime names are supported the same way as for `range_event_start!`.
+///
+/// # Example
+/// ```rust,ignore
+/// let request = sparkles_macro::global_range_start!("Request", id = request_id);
+/// response_sender.send((response, request));
+/// // On the writer thread
+/// sparkles_macro::range_event_end!(request, "Response written");
+/// ```
+#[proc_macro]
+pub fn global_range_start(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as EventInput);
+    if cfg!(feature = "off") {
+        return off::global_guard(&input.exprs());
+    }
+
+    let expanded = match &input.name {
+        EventName::Literal(name) if input.args.is_empty() => {
+            let (hash, meta) = input.event_meta(name, &[]);
+            quote! {
+                sparkles::global_range_start(#hash, #meta)
+            }
+        }
+        EventName::Literal(name) => {
+            let (arg_names, arg_values) = input.args();
+            let (hash, meta) = input.event_meta(name, &arg_names);
+            quote! {
+                sparkles::global_range_start_with_args(#hash, #meta, &[#(sparkles::EventArg::from(#arg_values)),*])
+            }
+        }
+        EventName::Dynamic(name) => {
+            if let Some(err) = input.dynamic_name_with_args_error(name) {
+                return err;
+            }
+            let name = EventName::dynamic_str(name);
+            quote! {
+                sparkles::global_range_start_dyn(#name)
+            }
+        }
+    };
+
+    TokenStream::from(input.category_check(expanded, Some(quote! { sparkles::GlobalRangeGuard::disabled() })))
+}
+
+/// Event name with arguments, which must be a string literal
+fn literal_event_meta<'a>(event: &'a EventInput, err_msg: &str) -> Result<(u32, TokenStream2, Vec<&'a Expr>), TokenStream> {
+    match &event.name {
+        EventName::Literal(name) => {
+            let (arg_names, arg_values) = event.args();
+            let (hash, meta) = event.event_meta(name, &arg_names);
+            Ok((hash, meta, arg_values))
+        }
+        EventName::Dynamic(name) => {
+            Err(syn::Error::new_spanned(name, err_msg).to_compile_error().into())
+        }
+    }
+}
+
+/// Start a new flow with instant event of given name
+///
+/// Returns `sparkles::FlowId`, which should be passed along with the work item to other threads.
+/// All flow events with the same id are connected with arrows in the trace viewer.
+/// Numeric arguments can be attached as `name = value` pairs. Only string literal names are supported.
+///
+/// # Example
+/// ```rust,ignore
+/// let flow = sparkles_macro::flow_begin!("Job enqueued", job_id = id);
+/// sender.send((job, flow));
+/// // On the worker thread
+/// sparkles_macro::flow_step!(flow, "Job started");
+/// sparkles_macro::flow_end!(flow, "Job finished");
+/// ```
+#[proc_macro]
+pub fn flow_begin(input: TokenStream) -> TokenStream {
+    let event = parse_macro_input!(input as EventInput);
+    if cfg!(feature = "off") {
+        return off::flow(&event.exprs());
+    }
+    let (hash, meta, arg_values) = match literal_event_meta(&event, "Flow event name must be a string literal") {
+        Ok(v) => v,
+        Err(err) => return err,
+    };
 
     let expanded = quote! {
-        sparkles::range_event_start(#hash, #s)
+        sparkles::flow_begin(#hash, #meta, &[#(sparkles::EventArg::from(#arg_values)),*])
     };
+    TokenStream::from(event.category_check(expanded, Some(quote! { sparkles::FlowId::disabled() })))
+}
 
-    TokenStream::from(expanded)
+struct FlowEventInput {
+    flow: Expr,
+    _comma: Comma,
+    event: EventInput,
+}
+
+impl Parse for FlowEventInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        Ok(Self {
+            flow: input.parse()?,
+            _comma: input.parse()?,
+            event: input.parse()?,
+        })
+    }
+}
+
+/// Record instant event with given name in the middle of the flow, started by `flow_begin!`
+///
+/// # Example
+/// ```rust,ignore
+/// sparkles_macro::flow_step!(flow, "Job started", worker = worker_id);
+/// ```
+#[proc_macro]
+pub fn flow_step(input: TokenStream) -> TokenStream {
+    let FlowEventInput{flow, event, ..} = parse_macro_input!(input as FlowEventInput);
+    if cfg!(feature = "off") {
+        return off::consume(&flow, &event.exprs());
+    }
+    let (hash, meta, arg_values) = match literal_event_meta(&event, "Flow event name must be a string literal") {
+        Ok(v) => v,
+        Err(err) => return err,
+    };
+
+    let expanded = quote! {
+        sparkles::flow_step(#flow, #hash, #meta, &[#(sparkles::EventArg::from(#arg_values)),*])
+    };
+    TokenStream::from(event.category_check(expanded, None))
+}
+
+/// Record instant event with given name, which terminates the flow, started by `flow_begin!`
+///
+/// # Example
+/// ```rust,ignore
+/// sparkles_macro::flow_end!(flow, "Job finished");
+/// ```
+#[proc_macro]
+pub fn flow_end(input: TokenStream) -> TokenStream {
+    let FlowEventInput{flow, event, ..} = parse_macro_input!(input as FlowEventInput);
+    if cfg!(feature = "off") {
+        return off::consume(&flow, &event.exprs());
+    }
+    let (hash, meta, arg_values) = match literal_event_meta(&event, "Flow event name must be a string literal") {
+        Ok(v) => v,
+        Err(err) => return err,
+    };
+
+    let expanded = quote! {
+        sparkles::flow_end(#flow, #hash, #meta, &[#(sparkles::EventArg::from(#arg_values)),*])
+    };
+    TokenStream::from(event.category_check(expanded, None))
 }
 
 struct RangeEventStartInput {
     guard: Expr,
     _comma: Comma,
-    name: LitStr,
+    name: EventName,
 }
 
 impl Parse for RangeEventStartInput {
@@ -68,9 +506,10 @@ impl Parse for RangeEventStartInput {
 
 /// Finish range event with given name
 /// If you don't want to assign name to the event end, simply drop the guard.
+/// Works both for `range_event_start!` and `global_range_start!` guards.
 ///
 /// # Example
-/// ```rust
+/// ```rust,ignore
 /// let packet_proc = sparkles_macro::range_event_start!("Packet parsing");
 /// // Do some work
 /// let Ok(data) = parse_packet(&packet) else {
@@ -81,21 +520,245 @@ impl Parse for RangeEventStartInput {
 #[proc_macro]
 pub fn range_event_end(input: TokenStream) -> TokenStream {
     let RangeEventStartInput{guard, name, ..} = parse_macro_input!(input as RangeEventStartInput);
-    let s = name.value();
-    let hash = calculate_hash(&s);
+    if cfg!(feature = "off") {
+        let exprs = match &name {
+            EventName::Dynamic(name) => vec![name],
+            EventName::Literal(_) => vec![],
+        };
+        return off::consume(&guard, &exprs);
+    }
 
-    let expanded = quote! {
-        #guard.end(#hash, #s)
+    let expanded = match name {
+        EventName::Literal(name) => {
+            let (hash, meta) = event_meta(&name.value(), &[], None);
+            quote! {
+                #guard.end(#hash, #meta)
+            }
+        }
+        EventName::Dynamic(name) => {
+            let name = EventName::dynamic_str(&name);
+            quote! {
+                #guard.end_dyn(#name)
+            }
+        }
     };
 
     TokenStream::from(expanded)
 }
 
-fn calculate_hash(s: &str) -> u32 {
-    use std::collections::hash_map::DefaultHasher;
-    use std::hash::{Hash, Hasher};
+struct CounterInput {
+    name: EventName,
+    _comma: Comma,
+    value: Expr,
+}
+
+impl Parse for CounterInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        Ok(Self {
+            name: input.parse()?,
+            _comma: input.parse()?,
+            value: input.parse()?,
+        })
+    }
+}
+
+/// Record a new value of the counter with given name
+///
+/// Counters are displayed as separate tracks with a plot of values over time.
+/// Any integer, float or bool value is accepted.
+///
+/// # Example
+/// ```rust,ignore
+/// sparkles_macro::counter!("Queue length", queue.len());
+/// ```
+#[proc_macro]
+pub fn counter(input: TokenStream) -> TokenStream {
+    let CounterInput{name, value, ..} = parse_macro_input!(input as CounterInput);
+    if cfg!(feature = "off") {
+        let exprs = match &name {
+            EventName::Dynamic(name) => vec![name, &value],
+            EventName::Literal(_) => vec![&value],
+        };
+        return off::unit(&exprs);
+    }
+
+    let expanded = match name {
+        EventName::Literal(name) => {
+            let (hash, meta) = event_meta(&name.value(), &[], None);
+            quote! {
+                sparkles::counter(#hash, #meta, sparkles::EventArg::from(#value))
+            }
+        }
+        EventName::Dynamic(name) => {
+            let name = EventName::dynamic_str(&name);
+            quote! {
+                sparkles::counter_dyn(#name, sparkles::EventArg::from(#value))
+            }
+        }
+    };
 
-    let mut hasher = DefaultHasher::new();
-    s.hash(&mut hasher);
-    hasher.finish() as u32
+    TokenStream::from(expanded)
+}
+
+struct InstrumentInput {
+    future: Expr,
+    _comma: Comma,
+    event: EventInput,
+}
+
+impl Parse for InstrumentInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        Ok(Self {
+            future: input.parse()?,
+            _comma: input.parse()?,
+            event: input.parse()?,
+        })
+    }
+}
+
+/// Wrap future in `sparkles::Instrumented`, which records a range event with given name for each poll
+///
+/// All polls are displayed on a separate task track, even if the future moves between threads.
+/// Numeric arguments can be attached the same way as for `range_event_start!`.
+/// Only string literal names are supported.
+///
+/// # Example
+/// ```rust,ignore
+/// let res = sparkles_macro::instrument!(download(url), "Download", attempt = i).await;
+/// ```
+#[proc_macro]
+pub fn instrument(input: TokenStream) -> TokenStream {
+    let InstrumentInput{future, event, ..} = parse_macro_input!(input as InstrumentInput);
+    if cfg!(feature = "off") {
+        return TokenStream::from(quote! { #future });
+    }
+
+    if let Some(category) = &event.category {
+        return syn::Error::new_spanned(category, "Categories are not supported for instrumented futures").to_compile_error().into();
+    }
+    let (hash, meta, arg_values) = match literal_event_meta(&event, "Instrumented future name must be a string literal") {
+        Ok(v) => v,
+        Err(err) => return err,
+    };
+
+    TokenStream::from(quote! {
+        sparkles::Instrumented::new(#future, #hash, #meta, ::std::vec![#(sparkles::EventArg::from(#arg_values)),*])
+    })
+}
+
+/// Wrap function body in a range event, named after the function
+///
+/// Optional properties:
+/// - `name = "..."` - use custom event name
+/// - `args(a, b, ...)` - attach values of selected numeric function arguments to the event
+///
+/// For `async fn`, the returned future is instrumented instead: range event is recorded for each poll.
+///
+/// # Example
+/// ```rust,ignore
+/// #[sparkles::trace(name = "Packet parsing", args(len))]
+/// fn parse_packet(data: &[u8], len: usize) -> Packet {
+///     // Do some work
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn trace(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let mut name: Option<LitStr> = None;
+    let mut args: Vec<Ident> = Vec::new();
+    let attr_parser = syn::meta::parser(|meta| {
+        if meta.path.is_ident("name") {
+            name = Some(meta.value()?.parse()?);
+            Ok(())
+        }
+        else if meta.path.is_ident("args") {
+            meta.parse_nested_meta(|arg| {
+                args.push(arg.path.require_ident()?.clone());
+                Ok(())
+            })
+        }
+        else {
+            Err(meta.error("unsupported trace property, expected `name` or `args`"))
+        }
+    });
+    parse_macro_input!(attr with attr_parser);
+    let item = parse_macro_input!(item as ItemFn);
+    if cfg!(feature = "off") {
+        return off::function(item, &args);
+    }
+    let ItemFn { attrs, vis, sig, block } = item;
+
+    let name = name.map(|name| name.value()).unwrap_or_else(|| sig.ident.to_string());
+    let arg_names: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+    let (hash, meta) = event_meta(&name, &arg_names, None);
+    let arg_values = quote! {
+        #(sparkles::EventArg::from(::core::clone::Clone::clone(&#args))),*
+    };
+
+    let body = if sig.asyncness.is_some() {
+        quote! {
+            sparkles::Instrumented::new(async move #block, #hash, #meta, ::std::vec![#arg_values]).await
+        }
+    }
+    else if args.is_empty() {
+        let stmts = &block.stmts;
+        quote! {
+            let _sparkles_guard = sparkles::range_event_start(#hash, #meta);
+            #(#stmts)*
+        }
+    }
+    else {
+        let stmts = &block.stmts;
+        quote! {
+            let _sparkles_guard = sparkles::range_event_start_with_args(#hash, #meta, &[#arg_values]);
+            #(#stmts)*
+        }
+    };
+
+    TokenStream::from(quote! {
+        #(#attrs)* #vis #sig {
+            #body
+        }
+    })
+}
+
+/// Create static event metadata with the call site location, and calculate hash for it.
+///
+/// Argument names and call site location are also hashed, so events with the same name,
+/// but different sets of arguments or recorded in different places will get different IDs.
+fn event_meta(name: &str, arg_names: &[String], category: Option<&str>) -> (u32, TokenStream2) {
+    let call_site = proc_macro::Span::call_site();
+
+    let mut key = name.to_string();
+    for arg_name in arg_names {
+        key.push('\0');
+        key.push_str(arg_name);
+    }
+    if let Some(category) = category {
+        key.push_str("\0category:");
+        key.push_str(category);
+    }
+    key.push_str(&format!("\0{}:{}:{}", call_site.file(), call_site.line(), call_site.column()));
+    let hash = calculate_hash(&key);
+    let category = match category {
+        Some(category) => quote! { Some(#category) },
+        None => quote! { None },
+    };
+
+    let meta = quote! {
+        {
+            static META: sparkles::EventMeta<'static> = sparkles::EventMeta {
+                name: #name,
+                arg_names: &[#(#arg_names),*],
+                location: Some(sparkles::SourceLocation::new(module_path!(), file!(), line!())),
+                category: #category,
+                is_static: true,
+            };
+            &META
+        }
+    };
+    (hash, meta)
+}
+
+fn calculate_hash(s: &str) -> u32 {
+    sparkles_core::hash::event_hash(s)
 }
\ No newline at end of file
@@ -30,10 +30,15 @@ fn main() {
         if let Some(filename) = path.file_name() {
             let filename = filename.to_string_lossy();
             if filename.ends_with(".sprk") {
-                let datetime = filename.trim_end_matches(".sprk");
+                let name = filename.trim_end_matches(".sprk");
+                // Files of the sessions, started within the same second, have `_N` suffix
+                let (datetime, index) = match name.rsplit_once('_') {
+                    Some((datetime, index)) if datetime.contains('_') => (datetime, index.parse::<u32>().unwrap_or(0)),
+                    _ => (name, 0),
+                };
                 let datetime = chrono::NaiveDateTime::parse_from_str(datetime, "%Y-%m-%d_%H-%M-%S");
                 if let Ok(datetime) = datetime {
-                    trace_files.push((datetime, path));
+                    trace_files.push(((datetime, index), path));
                 }
             }
         }
@@ -1,9 +1,12 @@
 use std::mem;
+use log::warn;
 use ringbuf::consumer::Consumer;
 use ringbuf::producer::Producer;
 use ringbuf::storage::Heap;
 use ringbuf::traits::Observer;
-use crate::{TracingEvent, TracingEventId, PARSER_BUF_SIZE};
+use sparkles_core::local_storage::args::EventArg;
+use sparkles_core::local_storage::links::EventLink;
+use crate::{RangeOrdId, TracingEvent, TracingEventId, PARSER_BUF_SIZE};
 
 pub struct StreamFrameDecoder {
     state: ParsingState,
@@ -19,18 +22,85 @@ impl Default for StreamFrameDecoder {
     }
 }
 
-#[derive(Copy,Clone, Default, Debug)]
+#[derive(Clone, Default, Debug)]
 #[derive(PartialEq)]
 pub enum ParsingState {
     #[default]
     NewFrame,
+    /// Continuation of the variable-length event id
+    EventId(PartialVarint),
     DifTmLen(TracingEventId),
 
-    /// id, dif_tm_len
-    DifTm(TracingEventId, usize),
+    /// id, dif_tm_len, extra fields
+    DifTm(TracingEventId, usize, EventExtras),
 
-    RangeOrdId(Option<TracingEventId>, usize),
-    RangeTm(Option<TracingEventId>, usize, u8)
+    /// id, dif_tm_len, extra fields, variable-length range ord id
+    RangeOrdId(Option<TracingEventId>, usize, EventExtras, PartialVarint),
+    RangeTm(Option<TracingEventId>, usize, RangeOrdId, EventExtras),
+
+    /// Event is decoded, waiting for attached link. event, remaining extra fields
+    LinkHeader(TracingEvent, EventExtras),
+    /// event, remaining extra fields, link kind, link len
+    LinkValue(TracingEvent, EventExtras, u8, usize),
+    /// Event is decoded, waiting for attached arguments
+    ArgsCount(TracingEvent, EventExtras),
+    /// event, remaining extra fields, remaining args count
+    ArgHeader(TracingEvent, EventExtras, u8),
+    /// event, remaining extra fields, remaining args count, arg kind, arg len
+    ArgValue(TracingEvent, EventExtras, u8, u8, usize),
+    /// Event is decoded, waiting for attached message
+    MessageLen(TracingEvent),
+    /// event, message len
+    MessageBytes(TracingEvent, usize),
+}
+
+/// Partially decoded variable-length integer
+#[derive(Clone, Copy, Default, Debug, PartialEq)]
+pub struct PartialVarint {
+    value: u32,
+    shift: u32,
+}
+
+impl PartialVarint {
+    /// Add next byte, returns the value if it was the last one. Bits above `u32` are dropped
+    fn push(self, byte: u8) -> Result<u32, Self> {
+        let value = self.value | ((byte & 0x7f) as u32).checked_shl(self.shift).unwrap_or(0);
+        if byte & 0x80 != 0 {
+            Err(Self {
+                value,
+                shift: self.shift + 7,
+            })
+        }
+        else {
+            Ok(value)
+        }
+    }
+}
+
+/// Optional fields, following the event timestamp
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EventExtras {
+    has_link: bool,
+    has_args: bool,
+    has_message: bool,
+}
+
+impl EventExtras {
+    /// Parsing state after the event timestamp is decoded
+    fn next_state(self, ev: TracingEvent) -> (Option<TracingEvent>, ParsingState) {
+        if self.has_link {
+            (None, ParsingState::LinkHeader(ev, EventExtras { has_link: false, ..self }))
+        }
+        else if self.has_args {
+            (None, ParsingState::ArgsCount(ev, EventExtras { has_args: false, ..self }))
+        }
+        else if self.has_message {
+            (None, ParsingState::MessageLen(ev))
+        }
+        else {
+            (Some(ev), ParsingState::NewFrame)
+        }
+    }
 }
 
 impl StreamFrameDecoder {
@@ -39,51 +109,142 @@ impl StreamFrameDecoder {
 
         let (ev, new_state) = match mem::take(&mut self.state) {
             ParsingState::NewFrame if available_bytes_len >= 1 => {
-                let ev_id = self.buf.try_pop().unwrap();
-                (None, ParsingState::DifTmLen(ev_id))
+                let byte = self.buf.try_pop().unwrap();
+                match PartialVarint::default().push(byte) {
+                    Ok(ev_id) => (None, ParsingState::DifTmLen(ev_id)),
+                    Err(ev_id) => (None, ParsingState::EventId(ev_id)),
+                }
+            }
+            ParsingState::EventId(ev_id) if available_bytes_len >= 1 => {
+                let byte = self.buf.try_pop().unwrap();
+                match ev_id.push(byte) {
+                    Ok(ev_id) => (None, ParsingState::DifTmLen(ev_id)),
+                    Err(ev_id) => (None, ParsingState::EventId(ev_id)),
+                }
             }
             ParsingState::DifTmLen(ev) if available_bytes_len >= 1 => {
                 let dif_tm_len = self.buf.try_pop().unwrap();
 
                 let is_range_event = dif_tm_len & 0b1000_0000 != 0;
                 let is_unnamed_range_end = dif_tm_len & 0b0100_0000 != 0;
+                let extras = EventExtras {
+                    has_link: dif_tm_len & 0b0001_0000 != 0,
+                    has_args: dif_tm_len & 0b0010_0000 != 0,
+                    // Same bit marks unnamed range end for range events
+                    has_message: !is_range_event && dif_tm_len & 0b0100_0000 != 0,
+                };
                 let dif_tm_len = (dif_tm_len & 0b0000_1111) as usize;
 
                 if is_range_event {
                     if is_unnamed_range_end {
-                        (None, ParsingState::RangeOrdId(None, dif_tm_len))
+                        (None, ParsingState::RangeOrdId(None, dif_tm_len, extras, PartialVarint::default()))
                     }
                     else {
-                        (None, ParsingState::RangeOrdId(Some(ev), dif_tm_len))
+                        (None, ParsingState::RangeOrdId(Some(ev), dif_tm_len, extras, PartialVarint::default()))
                     }
                 }
                 else {
-                    (None, ParsingState::DifTm(ev, dif_tm_len))
+                    (None, ParsingState::DifTm(ev, dif_tm_len, extras))
                 }
             }
-            ParsingState::DifTm(ev, dif_tm_len) if available_bytes_len >= dif_tm_len => {
+            ParsingState::DifTm(ev, dif_tm_len, extras) if available_bytes_len >= dif_tm_len => {
                 let mut buf = [0u8; 8];
                 self.buf.pop_slice(&mut buf[..dif_tm_len]);
                 let dif_tm = u64::from_le_bytes(buf);
-                (Some(TracingEvent::Instant(ev, dif_tm)), ParsingState::NewFrame)
+                let ev = TracingEvent::Instant(ev, dif_tm, None, Vec::new(), None);
+                extras.next_state(ev)
             }
-            ParsingState::RangeOrdId(ev, dif_tm_len) if available_bytes_len >= 1 => {
-                let ord_id = self.buf.try_pop().unwrap();
-
-                (None, ParsingState::RangeTm(ev, dif_tm_len, ord_id))
+            ParsingState::RangeOrdId(ev, dif_tm_len, extras, ord_id) if available_bytes_len >= 1 => {
+                let byte = self.buf.try_pop().unwrap();
+                match ord_id.push(byte) {
+                    Ok(ord_id) => (None, ParsingState::RangeTm(ev, dif_tm_len, ord_id, extras)),
+                    Err(ord_id) => (None, ParsingState::RangeOrdId(ev, dif_tm_len, extras, ord_id)),
+                }
             }
-            ParsingState::RangeTm(ev_id, dif_tm_len, ord_id) if available_bytes_len >= dif_tm_len => {
+            ParsingState::RangeTm(ev_id, dif_tm_len, ord_id, extras) if available_bytes_len >= dif_tm_len => {
                 let mut buf = [0u8; 8];
                 self.buf.pop_slice(&mut buf[..dif_tm_len]);
                 let dif_tm = u64::from_le_bytes(buf);
 
                 let ev = if let Some(id) = ev_id {
-                    Some(TracingEvent::RangePart(id, dif_tm, ord_id))
+                    TracingEvent::RangePart(id, dif_tm, ord_id, None, Vec::new())
                 }
                 else {
-                    Some(TracingEvent::UnnamedRangeEnd(dif_tm, ord_id))
+                    TracingEvent::UnnamedRangeEnd(dif_tm, ord_id)
                 };
-                (ev, ParsingState::NewFrame)
+                extras.next_state(ev)
+            }
+            ParsingState::LinkHeader(ev, extras) if available_bytes_len >= 1 => {
+                let link_header = self.buf.try_pop().unwrap();
+                let kind = link_header >> 4;
+                let len = (link_header & 0b0000_1111) as usize;
+
+                (None, ParsingState::LinkValue(ev, extras, kind, len))
+            }
+            ParsingState::LinkValue(mut ev, extras, kind, len) if available_bytes_len >= len => {
+                // Length is a 4-bit value, malformed link is skipped as a whole
+                let mut buf = [0u8; 15];
+                self.buf.pop_slice(&mut buf[..len]);
+                let link = EventLink::decode(kind, &buf[..len]);
+                if link.is_none() {
+                    warn!("Unknown link kind: {}, length: {}", kind, len);
+                }
+                if let Some(ev_link) = ev.link_mut() {
+                    *ev_link = link;
+                }
+
+                extras.next_state(ev)
+            }
+            ParsingState::ArgsCount(ev, extras) if available_bytes_len >= 1 => {
+                let args_cnt = self.buf.try_pop().unwrap();
+                if args_cnt == 0 {
+                    extras.next_state(ev)
+                }
+                else {
+                    (None, ParsingState::ArgHeader(ev, extras, args_cnt))
+                }
+            }
+            ParsingState::ArgHeader(ev, extras, remaining) if available_bytes_len >= 1 => {
+                let arg_header = self.buf.try_pop().unwrap();
+                let kind = arg_header >> 4;
+                let len = (arg_header & 0b0000_1111) as usize;
+
+                (None, ParsingState::ArgValue(ev, extras, remaining, kind, len))
+            }
+            ParsingState::ArgValue(mut ev, extras, remaining, kind, len) if available_bytes_len >= len => {
+                // Length is a 4-bit value, malformed argument is skipped as a whole
+                let mut buf = [0u8; 15];
+                self.buf.pop_slice(&mut buf[..len]);
+                let arg = EventArg::decode(kind, &buf[..len]).unwrap_or_else(|| {
+                    warn!("Unknown argument kind: {}, length: {}", kind, len);
+                    EventArg::Unsigned(0)
+                });
+                if let Some(args) = ev.args_mut() {
+                    args.push(arg);
+                }
+
+                if remaining == 1 {
+                    extras.next_state(ev)
+                }
+                else {
+                    (None, ParsingState::ArgHeader(ev, extras, remaining - 1))
+                }
+            }
+            ParsingState::MessageLen(ev) if available_bytes_len >= 2 => {
+                let mut buf = [0u8; 2];
+                self.buf.pop_slice(&mut buf);
+                let len = u16::from_le_bytes(buf) as usize;
+
+                (None, ParsingState::MessageBytes(ev, len))
+            }
+            ParsingState::MessageBytes(mut ev, len) if available_bytes_len >= len => {
+                let mut buf = vec![0u8; len];
+                self.buf.pop_slice(&mut buf);
+                if let TracingEvent::Instant(_, _, _, _, message) = &mut ev {
+                    *message = Some(String::from_utf8_lossy(&buf).into_owned());
+                }
+
+                (Some(ev), ParsingState::NewFrame)
             }
             state => {
                 // Not enough bytes
@@ -123,4 +284,198 @@ impl StreamFrameDecoder {
         assert!(self.buf.is_empty());
         assert_eq!(self.state, ParsingState::NewFrame);
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use std::cell::{Cell, RefCell};
+    use std::rc::Rc;
+    use sparkles_core::config::{BuffersLimitPolicy, LocalStorageConfig};
+    use sparkles_core::headers::LocalPacketHeader;
+    use sparkles_core::local_storage::id_mapping::{EventMeta, EventType};
+    use sparkles_core::local_storage::{GlobalStorageImpl, LocalStorage};
+    use super::*;
+
+    /// Collects encoded data of all flushed packets and counts reports of the lost events
+    #[derive(Clone, Default)]
+    struct CollectedBytes(Rc<RefCell<Vec<u8>>>, Rc<Cell<usize>>);
+
+    impl GlobalStorageImpl for CollectedBytes {
+        fn flush(&self, _header: &LocalPacketHeader, data: &[u8]) {
+            self.0.borrow_mut().extend_from_slice(data);
+        }
+        fn try_flush(&self, header: &LocalPacketHeader, data: &[u8]) -> bool {
+            self.flush(header, data);
+            true
+        }
+        fn is_buf_available(&self) -> bool {
+            true
+        }
+        fn report_lost(&self, header: &LocalPacketHeader) {
+            assert!(header.start_timestamp <= header.end_timestamp);
+            self.1.set(self.1.get() + 1);
+        }
+    }
+
+    /// Encode events with `record` and decode them back
+    fn round_trip(record: impl FnOnce(&mut LocalStorage<CollectedBytes>)) -> Vec<TracingEvent> {
+        let bytes = CollectedBytes::default();
+        let mut storage = LocalStorage::new(bytes.clone(), None, LocalStorageConfig::default());
+        record(&mut storage);
+        storage.flush(true);
+
+        let bytes = bytes.0.borrow();
+        decode(&bytes)
+    }
+
+    /// Decode events, bytes are fed in small chunks, so events are split between them
+    fn decode(bytes: &[u8]) -> Vec<TracingEvent> {
+        let mut decoder = StreamFrameDecoder::default();
+        let events = bytes.chunks(7).flat_map(|chunk| decoder.decode_many(chunk)).collect();
+        decoder.ensure_buf_end();
+        events
+    }
+
+    #[test]
+    fn args_round_trip() {
+        let args = [
+            EventArg::Unsigned(0),
+            EventArg::Unsigned(300),
+            EventArg::Unsigned(u64::MAX),
+            EventArg::Signed(-1),
+            EventArg::Signed(i64::MIN),
+            EventArg::Signed(i64::MAX),
+            EventArg::Float(-2.5),
+            EventArg::Float(f64::INFINITY),
+        ];
+        let events = round_trip(|storage| {
+            storage.event_instant_with_args(1, &EventMeta::new("instant"), &args);
+            let range = storage.event_range_start_with_args(2, &EventMeta::new("range"), &args[..3]);
+            storage.event_range_end(range, 0, &EventMeta::new(""));
+        });
+
+        assert_eq!(events, [
+            TracingEvent::Instant(0, events[0].dif_tm(), None, args.to_vec(), None),
+            TracingEvent::RangePart(1, events[1].dif_tm(), 0, None, args[..3].to_vec()),
+            TracingEvent::UnnamedRangeEnd(events[2].dif_tm(), 0),
+        ]);
+    }
+
+    #[test]
+    fn links_round_trip() {
+        let events = round_trip(|storage| {
+            let range = storage.event_range_start_with_link(1, &EventMeta::new("poll"), &[EventArg::Signed(-3)], EventLink::Task(u64::MAX));
+            storage.event_range_end(range, 0, &EventMeta::new(""));
+            let range = storage.event_range_start_with_link(1, &EventMeta::new("poll"), &[], EventLink::Task(0));
+            storage.event_range_end(range, 0, &EventMeta::new(""));
+            storage.event_global_range_start(2, &EventMeta::new("global"), &[], 1 << 40);
+            storage.event_flow(3, &EventMeta::new("flow"), &[EventArg::Float(1.5)], EventType::FlowBegin, 300);
+        });
+
+        assert_eq!(events, [
+            TracingEvent::RangePart(0, events[0].dif_tm(), 0, Some(EventLink::Task(u64::MAX)), vec![EventArg::Signed(-3)]),
+            TracingEvent::UnnamedRangeEnd(events[1].dif_tm(), 0),
+            TracingEvent::RangePart(0, events[2].dif_tm(), 0, Some(EventLink::Task(0)), Vec::new()),
+            TracingEvent::UnnamedRangeEnd(events[3].dif_tm(), 0),
+            TracingEvent::Instant(1, events[4].dif_tm(), Some(EventLink::GlobalRange(1 << 40)), Vec::new(), None),
+            TracingEvent::Instant(2, events[5].dif_tm(), Some(EventLink::Flow(300)), vec![EventArg::Float(1.5)], None),
+        ]);
+    }
+
+    #[test]
+    fn messages_round_trip() {
+        let long_message = "x".repeat(1000);
+        let events = round_trip(|storage| {
+            storage.event_instant_with_message(1, &EventMeta::new("empty"), &[], "");
+            storage.event_instant_with_message(2, &EventMeta::new("short"), &[EventArg::Unsigned(7)], "Message ✨");
+            storage.event_instant_with_message(3, &EventMeta::new("long"), &[], &long_message);
+        });
+
+        assert_eq!(events, [
+            TracingEvent::Instant(0, events[0].dif_tm(), None, Vec::new(), Some(String::new())),
+            TracingEvent::Instant(1, events[1].dif_tm(), None, vec![EventArg::Unsigned(7)], Some("Message ✨".to_string())),
+            TracingEvent::Instant(2, events[2].dif_tm(), None, Vec::new(), Some(long_message)),
+        ]);
+    }
+
+    #[test]
+    fn many_event_ids_round_trip() {
+        let names: Vec<String> = (0..20_000).map(|i| format!("event {}", i)).collect();
+        let events = round_trip(|storage| {
+            for (i, name) in names.iter().enumerate() {
+                let range = storage.event_range_start(i as u32 * 16, &EventMeta::new(name));
+                storage.event_instant(i as u32 * 16, &EventMeta::new(name));
+                storage.event_range_end(range, i as u32 * 16 + 8, &EventMeta::new("end"));
+            }
+        });
+
+        assert_eq!(events.len(), names.len() * 3);
+        for (i, events) in events.chunks(3).enumerate() {
+            let id = i as u32 * 3;
+            assert_eq!(events, [
+                TracingEvent::RangePart(id, events[0].dif_tm(), 0, None, Vec::new()),
+                TracingEvent::Instant(id + 1, events[1].dif_tm(), None, Vec::new(), None),
+                TracingEvent::RangePart(id + 2, events[2].dif_tm(), 0, None, Vec::new()),
+            ]);
+        }
+    }
+
+    #[test]
+    fn many_open_ranges_round_trip() {
+        let events = round_trip(|storage| {
+            let ranges: Vec<_> = (0..5000).map(|_| storage.event_range_start(1, &EventMeta::new("range"))).collect();
+            for range in ranges.into_iter().rev() {
+                storage.event_range_end(range, 0, &EventMeta::new(""));
+            }
+            // Ord id of the last finished range is reused
+            let range = storage.event_range_start(1, &EventMeta::new("range"));
+            storage.event_range_end(range, 0, &EventMeta::new(""));
+        });
+
+        assert_eq!(events.len(), 10_002);
+        for (i, event) in events[..5000].iter().enumerate() {
+            assert_eq!(event, &TracingEvent::RangePart(0, event.dif_tm(), i as u32, None, Vec::new()));
+        }
+        for (i, event) in events[5000..10_000].iter().enumerate() {
+            assert_eq!(event, &TracingEvent::UnnamedRangeEnd(event.dif_tm(), 4999 - i as u32));
+        }
+        assert_eq!(events[10_000], TracingEvent::RangePart(0, events[10_000].dif_tm(), 0, None, Vec::new()));
+    }
+
+    #[test]
+    fn events_over_buffers_limit_are_dropped() {
+        let bytes = CollectedBytes::default();
+        let config = LocalStorageConfig {
+            total_buffers_limit: Some(1),
+            buffers_limit_policy: BuffersLimitPolicy::Drop,
+            ..LocalStorageConfig::default()
+        };
+        let mut storage = LocalStorage::new(bytes.clone(), None, config);
+        for _ in 0..1000 {
+            storage.event_instant(1, &EventMeta::new("instant"));
+        }
+        storage.flush(true);
+
+        // Every growth of the buffer exceeds the limit, so only events of the last allocation are flushed
+        assert!(bytes.1.get() > 0);
+        assert!(decode(&bytes.0.borrow()).len() < 1000);
+    }
+
+    #[test]
+    fn malformed_lengths_are_skipped() {
+        let bytes = [
+            // Instant event with two arguments, first one is 12 bytes long
+            0x00, 0x20, 2, 0x0C, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 0x01, 5,
+            // Instant event with 9 bytes long link
+            0x01, 0x10, 0x09, 1, 2, 3, 4, 5, 6, 7, 8, 9,
+            // Correct instant event
+            0x02, 0x01, 42,
+        ];
+        let events = decode(&bytes);
+
+        assert_eq!(events, [
+            TracingEvent::Instant(0, 0, None, vec![EventArg::Unsigned(0), EventArg::Unsigned(5)], None),
+            TracingEvent::Instant(1, 0, None, Vec::new(), None),
+            TracingEvent::Instant(2, 42, None, Vec::new(), None),
+        ]);
+    }
+}
@@ -0,0 +1,67 @@
+use log::info;
+
+/// Frame duration statistics, all values are in nanoseconds
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrameStats {
+    pub count: usize,
+    pub avg: f64,
+    pub min: u64,
+    pub max: u64,
+    pub p50: u64,
+    pub p95: u64,
+    pub p99: u64,
+}
+
+impl FrameStats {
+    /// Calculate statistics for the frame durations. Durations are sorted in place. None if there are no frames
+    pub fn new(durations: &mut [u64]) -> Option<Self> {
+        if durations.is_empty() {
+            return None;
+        }
+        durations.sort_unstable();
+        let count = durations.len();
+        // Nearest-rank percentile
+        let percentile = |p: usize| durations[(count * p).div_ceil(100).max(1) - 1];
+        Some(Self {
+            count,
+            avg: durations.iter().sum::<u64>() as f64 / count as f64,
+            min: durations[0],
+            max: durations[count - 1],
+            p50: percentile(50),
+            p95: percentile(95),
+            p99: percentile(99),
+        })
+    }
+
+    pub fn log(&self) {
+        let ms = |ns: u64| ns as f64 / 1_000_000.0;
+        info!("Total frames: {}", self.count);
+        info!("Frame duration: avg {:.3} ms, min {:.3} ms, max {:.3} ms", self.avg / 1_000_000.0, ms(self.min), ms(self.max));
+        info!("Frame duration percentiles: p50 {:.3} ms, p95 {:.3} ms, p99 {:.3} ms", ms(self.p50), ms(self.p95), ms(self.p99));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frame_stats() {
+        assert_eq!(FrameStats::new(&mut []), None);
+
+        let mut durations: Vec<u64> = (1..=100).rev().collect();
+        let stats = FrameStats::new(&mut durations).unwrap();
+        assert_eq!(stats, FrameStats {
+            count: 100,
+            avg: 50.5,
+            min: 1,
+            max: 100,
+            p50: 50,
+            p95: 95,
+            p99: 99,
+        });
+
+        let stats = FrameStats::new(&mut [7]).unwrap();
+        assert_eq!((stats.p50, stats.p99), (7, 7));
+    }
+}
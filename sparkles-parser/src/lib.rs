@@ -1,15 +1,19 @@
 mod perfetto_format;
 mod consts;
 mod decoder;
+mod frames;
 
 use std::cmp::min;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashMap};
 use std::io::{Read, Write};
 use log::{debug, error, info, warn};
 use thiserror::Error;
-use sparkles_core::headers::{LocalPacketHeader, SparklesEncoderInfo};
-use sparkles_core::local_storage::id_mapping::EventType;
+use sparkles_core::headers::{BuildInfo, LocalPacketHeader, ProcessInfo, SparklesEncoderInfo, ENCODER_INFO_VERSION};
+use sparkles_core::local_storage::args::EventArg;
+use sparkles_core::local_storage::id_mapping::{EventTag, EventType};
+use sparkles_core::local_storage::links::EventLink;
 use crate::decoder::StreamFrameDecoder;
+use crate::frames::FrameStats;
 use crate::ParseError::Decode;
 use crate::perfetto_format::PerfettoTraceFile;
 
@@ -37,13 +41,37 @@ pub struct ThreadParserState {
 
     // ---- TMP DATA ----
     state_machine: StreamFrameDecoder,
-    // Helper for ranges handling
-    cur_started_ranges: BTreeMap<u8, (TracingEventId, u64)>,
+    // Helper for ranges handling: range_ord_id -> started range
+    cur_started_ranges: BTreeMap<RangeOrdId, StartedRange>,
     // Current timestamp, accumulated from events
     cur_tm: u64,
     zero_diff_cnt: u64,
 }
 
+/// Range start, waiting for the matching end event
+pub struct StartedRange {
+    start_id: TracingEventId,
+    start_tm: u64,
+    link: Option<EventLink>,
+    args: Vec<EventArg>,
+    // Flow events inside the range: (flow id, terminating)
+    flows: Vec<(u64, bool)>,
+}
+
+/// Frame boundary, recorded by `sparkles::new_frame`
+struct FrameMarker {
+    tag: EventTag,
+    timestamp: u64,
+    frame_index: u64,
+}
+
+/// Start or end of the global range, waiting for the matching part
+struct GlobalRangePart {
+    tag: EventTag,
+    timestamp: u64,
+    args: Vec<(String, EventArg)>,
+}
+
 #[derive(Debug, Error)]
 pub enum ParseError {
     #[error("Error while decoding frame")]
@@ -61,6 +89,81 @@ pub enum DecodeError {
 type ParseResult<T> = Result<T, ParseError>;
 type DecodeResult<T> = Result<T, DecodeError>;
 
+/// Decode encoder info packet of any layout version.
+/// Fields, missing in the info from older encoders, are left empty. Fields from newer encoders are skipped
+fn decode_encoder_info(mut info_bytes: &[u8]) -> bincode::Result<SparklesEncoderInfo> {
+    let reader = &mut info_bytes;
+    let (ver, process_name, pid, timestamp_max_value) = bincode::deserialize_from::<_, (u32, String, u32, u64)>(&mut *reader)?;
+    let mut info = SparklesEncoderInfo {
+        ver,
+        process_name,
+        pid,
+        timestamp_max_value,
+        build_info: BuildInfo::default(),
+        info_ver: 0,
+        process_info: ProcessInfo::default(),
+    };
+    if !reader.is_empty() {
+        info.build_info = bincode::deserialize_from(&mut *reader)?;
+        info.info_ver = 1;
+    }
+    if !reader.is_empty() {
+        info.info_ver = bincode::deserialize_from(&mut *reader)?;
+        info.process_info = bincode::deserialize_from(&mut *reader)?;
+    }
+    if info.info_ver > ENCODER_INFO_VERSION {
+        warn!("Encoder info is newer than parser! Parser: {}, Encoder: {}. Some information is skipped", ENCODER_INFO_VERSION, info.info_ver);
+    }
+    Ok(info)
+}
+
+/// Human-readable build information, saved as Perfetto process labels
+fn build_info_labels(build_info: &BuildInfo) -> Vec<String> {
+    let mut labels = Vec::new();
+    if let Some(crate_name) = &build_info.crate_name {
+        labels.push(format!("crate: {} {}", crate_name, build_info.crate_version.as_deref().unwrap_or("")).trim_end().to_string());
+    }
+    if let Some(git_revision) = &build_info.git_revision {
+        labels.push(format!("git: {}", git_revision));
+    }
+    if !build_info.profile.is_empty() {
+        labels.push(format!("profile: {}", build_info.profile));
+    }
+    if !build_info.target.is_empty() {
+        labels.push(format!("target: {}", build_info.target));
+    }
+    if !build_info.sparkles_features.is_empty() {
+        labels.push(format!("sparkles features: {}", build_info.sparkles_features.join(", ")));
+    }
+    labels
+}
+
+/// Human-readable process and host information, saved as Perfetto process labels
+fn process_info_labels(process_info: &ProcessInfo) -> Vec<String> {
+    let mut labels = Vec::new();
+    if let Some(hostname) = &process_info.hostname {
+        labels.push(format!("host: {}", hostname));
+    }
+    if !process_info.os.is_empty() {
+        let os_version = process_info.os_version.as_deref().map(|v| format!(" ({})", v)).unwrap_or_default();
+        labels.push(format!("os: {}{}", process_info.os, os_version));
+    }
+    if let Some(kernel_version) = &process_info.kernel_version {
+        labels.push(format!("kernel: {}", kernel_version));
+    }
+    if let Some(cpu_model) = &process_info.cpu_model {
+        labels.push(format!("cpu: {}", cpu_model));
+    }
+    if process_info.logical_cores != 0 {
+        labels.push(format!("logical cores: {}", process_info.logical_cores));
+    }
+    if process_info.start_time_unix_ns != 0 {
+        let start_time = chrono::DateTime::from_timestamp_nanos(process_info.start_time_unix_ns as i64);
+        labels.push(format!("started: {}", start_time.to_rfc3339_opts(chrono::SecondsFormat::Millis, true)));
+    }
+    labels
+}
+
 impl SparklesParser {
     /// Decode incoming events and save them to `trace.json` in Perfetto format
     pub fn parse_and_save(&mut self, mut reader: impl Read) -> ParseResult<()> {
@@ -83,19 +186,48 @@ impl SparklesParser {
 
         info!("Begin parsing... Encoder info: {:?}", encoder_info);
 
+        let build_labels = build_info_labels(&encoder_info.build_info);
+        for label in &build_labels {
+            info!("Build info: {}", label);
+        }
+        let process_labels = process_info_labels(&encoder_info.process_info);
+        if !encoder_info.process_info.cmdline.is_empty() {
+            info!("Process info: cmdline: {}", encoder_info.process_info.cmdline.join(" "));
+        }
+        for label in &process_labels {
+            info!("Process info: {}", label);
+        }
+
         let mut trace_res_file = PerfettoTraceFile::new(encoder_info.process_name, encoder_info.pid);
+        trace_res_file.add_process_labels(build_labels);
+        trace_res_file.add_process_labels(process_labels);
+        trace_res_file.set_cmdline(encoder_info.process_info.cmdline);
         let ticks_per_ns = self.ticks_per_ns.unwrap_or_else( || {
             warn!("Did not find timestamp frequency in decoded stream! Using default values");
             1.0
         });
+        // Global ranges can be started and finished on different threads, so they are matched after all threads are parsed
+        // global range id -> start/end event
+        let mut global_range_starts: HashMap<u64, GlobalRangePart> = HashMap::new();
+        let mut global_range_ends: HashMap<u64, GlobalRangePart> = HashMap::new();
+        // Frame markers may be recorded on any thread, so frames are built after all threads are parsed
+        let mut frame_markers: Vec<FrameMarker> = Vec::new();
         // iterate over all threads
         for (&thread_ord_id, parser_state) in &mut self.event_parsers {
             let thread_name = parser_state.thread_name.clone().unwrap_or("".to_string());
             let thread_id = parser_state.thread_id.unwrap_or(thread_ord_id);
+            parser_state.missed_events.sort_unstable();
+            let mut next_missed = 0;
             // iterate over events
             for (header, events) in &parser_state.event_buf {
                 trace_res_file.set_thread_name(thread_id, thread_name.clone());
 
+                let forgotten = forget_ranges_before_loss(&parser_state.missed_events, &mut next_missed,
+                                                          &mut parser_state.cur_started_ranges, header.start_timestamp);
+                if forgotten != 0 {
+                    warn!("Thread {}: {} ranges, started before the lost events, are not finished", thread_name, forgotten);
+                }
+
                 parser_state.cur_tm = header.start_timestamp;
                 let mut first = true;
                 for event in events {
@@ -104,12 +236,8 @@ impl SparklesParser {
                         first = false;
                     }
                     else {
-                        let dif_tm = match event {
-                            TracingEvent::Instant(_, dif_tm) => dif_tm,
-                            TracingEvent::RangePart(_, dif_tm, _) => dif_tm,
-                            TracingEvent::UnnamedRangeEnd(dif_tm, _) => dif_tm
-                        };
-                        if *dif_tm == 0 {
+                        let dif_tm = event.dif_tm();
+                        if dif_tm == 0 {
                             dif_tm_zero = true;
                         }
                         parser_state.cur_tm += dif_tm;
@@ -123,31 +251,107 @@ impl SparklesParser {
                     // add to trace file
                     let timestamp = (parser_state.cur_tm as f64 / ticks_per_ns) as u64 + parser_state.zero_diff_cnt * 10;
                     match event {
-                        TracingEvent::Instant(id, _) => {
-                            let (ev_name, _) = &header.id_store.tags[*id as usize];
-                            trace_res_file.add_point_event(ev_name.clone(), thread_id, timestamp);
+                        TracingEvent::Instant(id, _, link, args, message) => {
+                            let tag = &header.id_store.tags[*id as usize];
+                            match (&tag.event_type, link) {
+                                (EventType::Counter, _) => {
+                                    if let Some(value) = args.first() {
+                                        trace_res_file.add_counter_value(&tag.name, timestamp, *value);
+                                    }
+                                    else {
+                                        warn!("Counter event {} has no value!", tag.name);
+                                    }
+                                }
+                                (EventType::Frame, _) => {
+                                    let frame_index = match args.first() {
+                                        Some(EventArg::Unsigned(frame_index)) => *frame_index,
+                                        _ => frame_markers.len() as u64,
+                                    };
+                                    frame_markers.push(FrameMarker {
+                                        tag: tag.clone(),
+                                        timestamp,
+                                        frame_index,
+                                    });
+                                }
+                                (EventType::GlobalRangeStart, Some(EventLink::GlobalRange(range_id))) => {
+                                    global_range_starts.insert(*range_id, GlobalRangePart {
+                                        tag: tag.clone(),
+                                        timestamp,
+                                        args: named_args(&tag.arg_names, args),
+                                    });
+                                }
+                                (EventType::GlobalRangeEnd, Some(EventLink::GlobalRange(range_id))) => {
+                                    global_range_ends.insert(*range_id, GlobalRangePart {
+                                        tag: tag.clone(),
+                                        timestamp,
+                                        args: Vec::new(),
+                                    });
+                                }
+                                (EventType::FlowBegin | EventType::FlowStep | EventType::FlowEnd, Some(EventLink::Flow(flow_id))) => {
+                                    let terminating = matches!(tag.event_type, EventType::FlowEnd);
+                                    // Perfetto draws flow arrows between slices, so the flow is attached to the innermost open range.
+                                    // Flow event itself is kept as a plain instant with its name and arguments
+                                    let enclosing_range = parser_state.cur_started_ranges.values_mut().max_by_key(|range| range.start_tm);
+                                    if let Some(range) = enclosing_range {
+                                        range.flows.push((*flow_id, terminating));
+                                        trace_res_file.add_point_event(tag.name.clone(), thread_id, timestamp, named_args(&tag.arg_names, args), tag);
+                                    }
+                                    else {
+                                        trace_res_file.add_flow_event(tag.name.clone(), thread_id, timestamp, named_args(&tag.arg_names, args), tag,
+                                                                      *flow_id, terminating);
+                                    }
+                                }
+                                _ => {
+                                    if let Some(message) = message {
+                                        trace_res_file.add_message_event(tag.name.clone(), thread_id, timestamp, named_args(&tag.arg_names, args), message.clone(), tag);
+                                    }
+                                    else {
+                                        trace_res_file.add_point_event(tag.name.clone(), thread_id, timestamp, named_args(&tag.arg_names, args), tag);
+                                    }
+                                }
+                            }
                         }
-                        TracingEvent::RangePart(id, _, ord_id) => {
-                            let (ev_name, ev_type) = &header.id_store.tags[*id as usize];
-                            if let EventType::RangeEnd(start_id) = ev_type {
-                                let (start_name, _) = &header.id_store.tags[*start_id as usize];
-                                let start_info = parser_state.cur_started_ranges.remove(ord_id).unwrap();
-                                let start_tm = start_info.1;
-                                let end_tm = timestamp;
-                                trace_res_file.add_range_event(format!("{} -> {}", start_name, ev_name), thread_id, start_tm, end_tm);
+                        TracingEvent::RangePart(id, _, ord_id, link, args) => {
+                            let tag = &header.id_store.tags[*id as usize];
+                            if let EventType::RangeEnd(start_id) = tag.event_type {
+                                let start_tag = &header.id_store.tags[start_id as usize];
+                                let Some(range) = parser_state.cur_started_ranges.remove(ord_id) else {
+                                    warn!("Range end {} has no matching start!", tag.name);
+                                    continue;
+                                };
+                                let name = format!("{} -> {}", start_tag.name, tag.name);
+                                let args = named_args(&start_tag.arg_names, &range.args);
+                                if let Some(EventLink::Task(task_id)) = range.link {
+                                    trace_res_file.add_task_range_event(name.clone(), task_id, range.start_tm, timestamp, args.clone(), start_tag);
+                                }
+                                trace_res_file.add_range_event(name, thread_id, range.start_tm, timestamp, args, start_tag, &range.flows);
                             }
                             else {
                                 // Range start
-                                parser_state.cur_started_ranges.insert(*ord_id, (*id, timestamp));
+                                let replaced = parser_state.cur_started_ranges.insert(*ord_id, StartedRange {
+                                    start_id: *id,
+                                    start_tm: timestamp,
+                                    link: *link,
+                                    args: args.clone(),
+                                    flows: Vec::new(),
+                                });
+                                if let Some(replaced) = replaced {
+                                    let replaced_name = header.id_store.tags.get(replaced.start_id as usize).map_or("", |tag| tag.name.as_ref());
+                                    warn!("Range {} has no end, range {} is started with the same ord id!", replaced_name, tag.name);
+                                }
                             }
                         }
                         TracingEvent::UnnamedRangeEnd(_, ord_id ) => {
-                            let start_info = parser_state.cur_started_ranges.remove(ord_id).unwrap();
-                            let range_id = start_info.0;
-                            let range_name = &header.id_store.tags[range_id as usize].0;
-                            let start_tm = start_info.1;
-                            let end_tm = timestamp;
-                            trace_res_file.add_range_event(range_name.clone(), thread_id, start_tm, end_tm);
+                            let Some(range) = parser_state.cur_started_ranges.remove(ord_id) else {
+                                warn!("Unnamed range end has no matching start!");
+                                continue;
+                            };
+                            let start_tag = &header.id_store.tags[range.start_id as usize];
+                            let args = named_args(&start_tag.arg_names, &range.args);
+                            if let Some(EventLink::Task(task_id)) = range.link {
+                                trace_res_file.add_task_range_event(start_tag.name.clone(), task_id, range.start_tm, timestamp, args.clone(), start_tag);
+                            }
+                            trace_res_file.add_range_event(start_tag.name.clone(), thread_id, range.start_tm, timestamp, args, start_tag, &range.flows);
                         }
                     }
                 }
@@ -161,6 +365,53 @@ impl SparklesParser {
                 covered_dur += header.end_timestamp - header.start_timestamp;
 
             }
+
+            // Packets, dropped by the global storage cleanup or because of the thread-local buffers limit
+            if !parser_state.missed_events.is_empty() {
+                trace_res_file.set_thread_name(thread_id, thread_name.clone());
+                let mut lost_dur = 0;
+                for &(start, dur) in &parser_state.missed_events {
+                    let begin = (start as f64 / ticks_per_ns) as u64;
+                    let end = ((start + dur) as f64 / ticks_per_ns) as u64;
+                    trace_res_file.add_lost_events(thread_id, begin, end);
+                    lost_dur += end - begin;
+                }
+                warn!("Thread {}: {} event packets were lost, {:.3} ms in total", thread_name, parser_state.missed_events.len(), lost_dur as f64 / 1_000_000.0);
+            }
+        }
+
+        // Match global range starts and ends
+        let mut global_ranges: Vec<_> = global_range_starts.into_iter().filter_map(|(range_id, start)| {
+            let end = global_range_ends.remove(&range_id);
+            if end.is_none() {
+                warn!("Global range {} was not finished!", start.tag.name);
+            }
+            end.map(|end| (start, end))
+        }).collect();
+        if !global_range_ends.is_empty() {
+            warn!("{} global range ends have no matching start!", global_range_ends.len());
+        }
+        global_ranges.sort_by_key(|(start, _)| start.timestamp);
+        for (start, end) in global_ranges {
+            let name = if end.tag.name.is_empty() {
+                start.tag.name.clone()
+            }
+            else {
+                format!("{} -> {}", start.tag.name, end.tag.name)
+            };
+            trace_res_file.add_global_range_event(&start.tag.name, name, start.timestamp, end.timestamp, start.args, &start.tag);
+        }
+
+        // Each frame lasts until the next frame marker, so the last frame is not finished
+        frame_markers.sort_by_key(|marker| marker.timestamp);
+        let mut frame_durations = Vec::new();
+        for frame in frame_markers.windows(2) {
+            let (start, end) = (&frame[0], &frame[1]);
+            trace_res_file.add_frame_event(format!("Frame {}", start.frame_index), start.timestamp, end.timestamp, &start.tag);
+            frame_durations.push(end.timestamp - start.timestamp);
+        }
+        if let Some(stats) = FrameStats::new(&mut frame_durations) {
+            stats.log();
         }
 
         let events_per_sec = total_events as f64 / ((max_timestamp - min_timestamp) as f64 / ticks_per_ns) * 1_000_000_000.0;
@@ -198,7 +449,7 @@ impl SparklesParser {
 
                     let mut info_bytes = vec![0u8; info_bytes_len];
                     con.read_exact(&mut info_bytes)?;
-                    let info = bincode::deserialize::<SparklesEncoderInfo>(&info_bytes)?;
+                    let info = decode_encoder_info(&info_bytes)?;
 
                     if info.ver != consts::ENCODER_VERSION {
                         warn!("Encoder version mismatch! Parser: {}, Encoder: {}", consts::ENCODER_VERSION, info.ver);
@@ -250,7 +501,7 @@ impl SparklesParser {
 
                             let new_events = cur_parser_state.state_machine.decode_many(&events_bytes);
                             let new_events_len = new_events.len();
-                            event_buf.extend_from_slice(&new_events);
+                            event_buf.extend(new_events);
                             debug!("Got {} bytes, Parsed {} events", cur_size, new_events_len);
                             self.total_event_bytes += cur_size as u64;
 
@@ -306,12 +557,124 @@ impl SparklesParser {
     }
 }
 
-pub type TracingEventId = u8;
+/// Forget ranges, started before the lost packets, which precede the packet with `packet_start` timestamp.
+/// Ends of these ranges may be lost, and their ord ids may be reused after the loss, so they can't be matched anymore.
+/// `next_missed` is the index of the first lost packet after the previous packet. Returns amount of forgotten ranges
+fn forget_ranges_before_loss(missed_events: &[(u64, u64)], next_missed: &mut usize,
+                             started_ranges: &mut BTreeMap<RangeOrdId, StartedRange>, packet_start: u64) -> usize {
+    let lost_cnt = missed_events[*next_missed..].iter().take_while(|(start, _)| *start < packet_start).count();
+    if lost_cnt == 0 {
+        return 0;
+    }
+    *next_missed += lost_cnt;
+    let forgotten = started_ranges.len();
+    started_ranges.clear();
+    forgotten
+}
+
+/// Match decoded argument values with argument names from the event tag
+fn named_args(arg_names: &[String], args: &[EventArg]) -> Vec<(String, EventArg)> {
+    arg_names.iter().cloned().zip(args.iter().copied()).collect()
+}
+
+pub type TracingEventId = u32;
+/// Matches range start with its end within the thread
+pub type RangeOrdId = u32;
 
-/// event, dif_tm
-#[derive(Debug, Copy, Clone)]
+/// event, dif_tm, (range_ord_id), (link), (args), (message)
+#[derive(Debug, Clone, PartialEq)]
 pub enum TracingEvent {
-    Instant(TracingEventId, u64),
-    RangePart(TracingEventId, u64, u8),
-    UnnamedRangeEnd(u64, u8)
+    Instant(TracingEventId, u64, Option<EventLink>, Vec<EventArg>, Option<String>),
+    RangePart(TracingEventId, u64, RangeOrdId, Option<EventLink>, Vec<EventArg>),
+    UnnamedRangeEnd(u64, RangeOrdId)
+}
+
+impl TracingEvent {
+    pub fn dif_tm(&self) -> u64 {
+        match self {
+            TracingEvent::Instant(_, dif_tm, _, _, _) => *dif_tm,
+            TracingEvent::RangePart(_, dif_tm, _, _, _) => *dif_tm,
+            TracingEvent::UnnamedRangeEnd(dif_tm, _) => *dif_tm
+        }
+    }
+
+    /// Attached link storage, if event type supports it
+    pub fn link_mut(&mut self) -> Option<&mut Option<EventLink>> {
+        match self {
+            TracingEvent::Instant(_, _, link, _, _) => Some(link),
+            TracingEvent::RangePart(_, _, _, link, _) => Some(link),
+            TracingEvent::UnnamedRangeEnd(_, _) => None
+        }
+    }
+
+    /// Attached arguments storage, if event type supports it
+    pub fn args_mut(&mut self) -> Option<&mut Vec<EventArg>> {
+        match self {
+            TracingEvent::Instant(_, _, _, args, _) => Some(args),
+            TracingEvent::RangePart(_, _, _, _, args) => Some(args),
+            TracingEvent::UnnamedRangeEnd(_, _) => None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encoder_info_versions() {
+        let legacy_info = bincode::serialize(&(3u32, "app".to_string(), 42u32, u64::MAX)).unwrap();
+        let info = decode_encoder_info(&legacy_info).unwrap();
+        assert_eq!((info.ver, info.process_name.as_str(), info.pid), (3, "app", 42));
+        assert!(info.build_info.crate_name.is_none());
+
+        let mut info = SparklesEncoderInfo::new("app".to_string(), 42);
+        info.build_info.git_revision = Some("0123456789ab".to_string());
+        info.process_info.hostname = Some("ci-runner".to_string());
+        let info_bytes = bincode::serialize(&info).unwrap();
+
+        // Encoder info with build info only
+        let build_info_len = bincode::serialized_size(&info.build_info).unwrap() as usize;
+        let base_len = legacy_info.len();
+        let info = decode_encoder_info(&info_bytes[..base_len + build_info_len]).unwrap();
+        assert_eq!(info.info_ver, 1);
+        assert_eq!(info.build_info.git_revision.as_deref(), Some("0123456789ab"));
+        assert!(info.process_info.hostname.is_none());
+
+        // Fields from newer encoders are skipped
+        let mut newer_info = info_bytes.clone();
+        newer_info.extend_from_slice(&[1, 2, 3]);
+        for info_bytes in [info_bytes, newer_info] {
+            let info = decode_encoder_info(&info_bytes).unwrap();
+            assert_eq!(info.info_ver, ENCODER_INFO_VERSION);
+            assert_eq!(info.build_info.git_revision.as_deref(), Some("0123456789ab"));
+            assert_eq!(info.process_info.hostname.as_deref(), Some("ci-runner"));
+        }
+    }
+
+    #[test]
+    fn ranges_are_forgotten_after_lost_packets() {
+        let started = |start_tm| StartedRange {
+            start_id: 0,
+            start_tm,
+            link: None,
+            args: Vec::new(),
+            flows: Vec::new(),
+        };
+        let missed_events = [(100, 10), (300, 10)];
+        let mut next_missed = 0;
+        let mut started_ranges = BTreeMap::new();
+
+        started_ranges.insert(0, started(50));
+        assert_eq!(forget_ranges_before_loss(&missed_events, &mut next_missed, &mut started_ranges, 80), 0);
+        // Range 0 was started before the first loss, ord id 0 may be reused after it
+        assert_eq!(forget_ranges_before_loss(&missed_events, &mut next_missed, &mut started_ranges, 200), 1);
+        assert!(started_ranges.is_empty());
+
+        started_ranges.insert(0, started(210));
+        assert_eq!(forget_ranges_before_loss(&missed_events, &mut next_missed, &mut started_ranges, 250), 0);
+        assert_eq!(started_ranges.len(), 1);
+        assert_eq!(forget_ranges_before_loss(&missed_events, &mut next_missed, &mut started_ranges, 400), 1);
+        assert_eq!(next_missed, 2);
+    }
 }
\ No newline at end of file
@@ -1,6 +1,8 @@
 use std::collections::HashMap;
 use prost::bytes::BytesMut;
 use prost::Message;
+use sparkles_core::local_storage::args::EventArg;
+use sparkles_core::local_storage::id_mapping::{EventTag, EventType, SourceLocation};
 use crate::perfetto_format::decl::trace_packet::{Data, OptionalTrustedPacketSequenceId};
 use crate::perfetto_format::decl::TracePacket;
 
@@ -12,6 +14,12 @@ pub struct PerfettoTraceFile {
     trace: decl::Trace,
     proc_descriptor: decl::TrackDescriptor,
     thread_descriptors: HashMap<u64, decl::TrackDescriptor>,
+    counter_descriptors: HashMap<String, decl::TrackDescriptor>,
+    // task_id -> (track descriptor, start timestamp of the earliest range)
+    task_descriptors: HashMap<u64, (decl::TrackDescriptor, u64)>,
+    // global range name -> list of (track descriptor, end timestamp of the last range)
+    global_range_descriptors: HashMap<String, Vec<(decl::TrackDescriptor, u64)>>,
+    frames_descriptor: Option<decl::TrackDescriptor>,
 
     sequence_id: u32,
     pid: i32,
@@ -41,53 +49,247 @@ impl PerfettoTraceFile {
             trace,
             proc_descriptor,
             thread_descriptors,
+            counter_descriptors: HashMap::new(),
+            task_descriptors: HashMap::new(),
+            global_range_descriptors: HashMap::new(),
+            frames_descriptor: None,
             sequence_id: Self::new_uuid() as u32,
             pid: pid as i32,
         }
     }
 
-    pub fn add_range_event(&mut self, name: String, thread_id: u64, begin: u64, end: u64) {
+    fn push_track_event(&mut self, timestamp: u64, track_event: decl::TrackEvent) {
+        let packet = decl::TracePacket {
+            timestamp: Some(timestamp),
+            data: Some(Data::TrackEvent(track_event)),
+            optional_trusted_packet_sequence_id: Some(OptionalTrustedPacketSequenceId::TrustedPacketSequenceId(self.sequence_id)),
+            ..Default::default()
+        };
+        self.trace.packet.push(packet);
+    }
+
+    fn debug_annotations(args: Vec<(String, EventArg)>) -> Vec<decl::DebugAnnotation> {
+        args.into_iter().map(|(name, arg)| {
+            let value = match arg {
+                EventArg::Unsigned(v) => decl::debug_annotation::Value::UintValue(v),
+                EventArg::Signed(v) => decl::debug_annotation::Value::IntValue(v),
+                EventArg::Float(v) => decl::debug_annotation::Value::DoubleValue(v),
+            };
+            decl::DebugAnnotation {
+                name_field: Some(decl::debug_annotation::NameField::Name(name)),
+                value: Some(value),
+                ..Default::default()
+            }
+        }).collect()
+    }
+
+    fn source_location(location: Option<&SourceLocation>) -> Option<decl::track_event::SourceLocationField> {
+        location.map(|location| {
+            decl::track_event::SourceLocationField::SourceLocation(decl::SourceLocation {
+                // Function name is not known at the call site, so the module path is shown in its place
+                file_name: Some(location.file.to_string()),
+                function_name: Some(location.module_path.to_string()),
+                line_number: Some(location.line),
+                ..Default::default()
+            })
+        })
+    }
+
+    /// Add range event to the thread track. `flows` are flow events, which happened inside the range: (flow id, terminating)
+    #[allow(clippy::too_many_arguments)]
+    pub fn add_range_event(&mut self, name: String, thread_id: u64, begin: u64, end: u64, args: Vec<(String, EventArg)>, tag: &EventTag,
+                           flows: &[(u64, bool)]) {
         let uuid = self.uuid_for_thread_id(thread_id);
+        self.add_slice(uuid, name, begin, end, args, tag, flows);
+    }
+
+    /// Add range event to the async task track. Track is created on the first event and named after the earliest range
+    pub fn add_task_range_event(&mut self, name: String, task_id: u64, begin: u64, end: u64, args: Vec<(String, EventArg)>, tag: &EventTag) {
+        let proc_uuid = self.proc_descriptor.uuid.unwrap();
+        let (descriptor, first_begin) = self.task_descriptors.entry(task_id).or_insert_with(|| {
+            let descriptor = decl::TrackDescriptor {
+                parent_uuid: Some(proc_uuid),
+                uuid: Some(Self::new_uuid()),
+                ..Default::default()
+            };
+            (descriptor, u64::MAX)
+        });
+        if begin < *first_begin {
+            *first_begin = begin;
+            descriptor.static_or_dynamic_name = Some(decl::track_descriptor::StaticOrDynamicName::Name(format!("Task #{}: {}", task_id, name)));
+        }
+        let uuid = descriptor.uuid.unwrap();
 
+        self.add_slice(uuid, name, begin, end, args, tag, &[]);
+    }
+
+    /// Add global range to the async track. Ranges must be added in order of their start.
+    ///
+    /// Ranges with the same `track_name` share a group of tracks: new track is created only when all existing ones are busy.
+    pub fn add_global_range_event(&mut self, track_name: &str, name: String, begin: u64, end: u64, args: Vec<(String, EventArg)>, tag: &EventTag) {
+        let proc_uuid = self.proc_descriptor.uuid.unwrap();
+        let tracks = self.global_range_descriptors.entry(track_name.to_string()).or_default();
+        let free_track = tracks.iter().position(|(_, last_end)| *last_end <= begin);
+        let track_idx = free_track.unwrap_or_else(|| {
+            tracks.push((decl::TrackDescriptor {
+                static_or_dynamic_name: Some(decl::track_descriptor::StaticOrDynamicName::Name(track_name.to_string())),
+                parent_uuid: Some(proc_uuid),
+                uuid: Some(Self::new_uuid()),
+                ..Default::default()
+            }, 0));
+            tracks.len() - 1
+        });
+        let (descriptor, last_end) = &mut tracks[track_idx];
+        *last_end = end;
+        let uuid = descriptor.uuid.unwrap();
+
+        self.add_slice(uuid, name, begin, end, args, tag, &[]);
+    }
+
+    /// Add frame to the dedicated `Frames` track. Track is created on the first frame
+    pub fn add_frame_event(&mut self, name: String, begin: u64, end: u64, tag: &EventTag) {
+        let proc_uuid = self.proc_descriptor.uuid.unwrap();
+        let uuid = self.frames_descriptor.get_or_insert_with(|| {
+            decl::TrackDescriptor {
+                static_or_dynamic_name: Some(decl::track_descriptor::StaticOrDynamicName::Name("Frames".to_string())),
+                parent_uuid: Some(proc_uuid),
+                uuid: Some(Self::new_uuid()),
+                ..Default::default()
+            }
+        }).uuid.unwrap();
+
+        self.add_slice(uuid, name, begin, end, Vec::new(), tag, &[]);
+    }
+
+    /// Mark time range on the thread track, where recorded events were lost
+    pub fn add_lost_events(&mut self, thread_id: u64, begin: u64, end: u64) {
+        let tag = EventTag {
+            name: "Events lost".to_string(),
+            event_type: EventType::RangeStart,
+            arg_names: Vec::new(),
+            location: None,
+            category: None,
+        };
+        let uuid = self.uuid_for_thread_id(thread_id);
+        self.add_slice(uuid, tag.name.clone(), begin, end, Vec::new(), &tag, &[]);
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn add_slice(&mut self, uuid: u64, name: String, begin: u64, end: u64, args: Vec<(String, EventArg)>, tag: &EventTag,
+                 flows: &[(u64, bool)]) {
         let mut track_event = decl::TrackEvent::default();
         track_event.name_field = Some(decl::track_event::NameField::Name(name));
         track_event.set_type(decl::track_event::Type::SliceBegin);
         track_event.track_uuid = Some(uuid);
+        track_event.debug_annotations = Self::debug_annotations(args);
+        track_event.source_location_field = Self::source_location(tag.location.as_ref());
+        track_event.categories = tag.category.iter().cloned().collect();
+        for &(flow_id, terminating) in flows {
+            if terminating {
+                track_event.terminating_flow_ids.push(flow_id);
+            }
+            else {
+                track_event.flow_ids.push(flow_id);
+            }
+        }
 
-        let mut packet = decl::TracePacket::default();
-        packet.timestamp = Some(begin);
-        packet.data = Some(Data::TrackEvent(track_event));
-        packet.optional_trusted_packet_sequence_id = Some(OptionalTrustedPacketSequenceId::TrustedPacketSequenceId(self.sequence_id));
-
-        self.trace.packet.push(packet);
+        self.push_track_event(begin, track_event);
 
         let mut track_event = decl::TrackEvent::default();
         track_event.set_type(decl::track_event::Type::SliceEnd);
         track_event.track_uuid = Some(uuid);
 
-        let mut packet = decl::TracePacket::default();
-        packet.timestamp = Some(end);
-        packet.data = Some(Data::TrackEvent(track_event));
-        packet.optional_trusted_packet_sequence_id = Some(OptionalTrustedPacketSequenceId::TrustedPacketSequenceId(self.sequence_id));
-
-        self.trace.packet.push(packet);
+        self.push_track_event(end, track_event);
     }
 
-    pub fn add_point_event(&mut self, name: String, thread_id: u64, timestamp: u64) {
+    fn point_track_event(&self, name: String, thread_id: u64, args: Vec<(String, EventArg)>, tag: &EventTag) -> decl::TrackEvent {
         let uuid = self.uuid_for_thread_id(thread_id);
 
         let mut track_event = decl::TrackEvent::default();
         track_event.name_field = Some(decl::track_event::NameField::Name(name));
         track_event.set_type(decl::track_event::Type::Instant);
         track_event.track_uuid = Some(uuid);
+        track_event.debug_annotations = Self::debug_annotations(args);
+        track_event.source_location_field = Self::source_location(tag.location.as_ref());
+        track_event.categories = tag.category.iter().cloned().collect();
+        track_event
+    }
 
-        let mut packet = decl::TracePacket::default();
-        packet.timestamp = Some(timestamp);
-        packet.data = Some(Data::TrackEvent(track_event));
-        packet.optional_trusted_packet_sequence_id = Some(OptionalTrustedPacketSequenceId::TrustedPacketSequenceId(self.sequence_id));
+    pub fn add_point_event(&mut self, name: String, thread_id: u64, timestamp: u64, args: Vec<(String, EventArg)>, tag: &EventTag) {
+        let track_event = self.point_track_event(name, thread_id, args, tag);
+        self.push_track_event(timestamp, track_event);
+    }
 
-        self.trace.packet.push(packet);
+    /// Add point event with attached text message, saved as `message` debug annotation
+    pub fn add_message_event(&mut self, name: String, thread_id: u64, timestamp: u64, args: Vec<(String, EventArg)>, message: String, tag: &EventTag) {
+        let mut track_event = self.point_track_event(name, thread_id, args, tag);
+        track_event.debug_annotations.push(decl::DebugAnnotation {
+            name_field: Some(decl::debug_annotation::NameField::Name("message".to_string())),
+            value: Some(decl::debug_annotation::Value::StringValue(message)),
+            ..Default::default()
+        });
+        self.push_track_event(timestamp, track_event);
+    }
+
+    /// Add point event, connected with other events of the same flow. `terminating` event is the last one in the flow.
+    ///
+    /// Used only for flow events outside of any range: otherwise the flow is attached to the enclosing range with `add_range_event`
+    #[allow(clippy::too_many_arguments)]
+    pub fn add_flow_event(&mut self, name: String, thread_id: u64, timestamp: u64, args: Vec<(String, EventArg)>, tag: &EventTag,
+                          flow_id: u64, terminating: bool) {
+        let mut track_event = self.point_track_event(name, thread_id, args, tag);
+        if terminating {
+            track_event.terminating_flow_ids.push(flow_id);
+        }
+        else {
+            track_event.flow_ids.push(flow_id);
+        }
+        self.push_track_event(timestamp, track_event);
+    }
+
+    /// Add new value to the counter track. Track is created on the first value
+    pub fn add_counter_value(&mut self, name: &str, timestamp: u64, value: EventArg) {
+        let proc_uuid = self.proc_descriptor.uuid.unwrap();
+        let uuid = self.counter_descriptors.entry(name.to_string()).or_insert_with(|| {
+            decl::TrackDescriptor {
+                counter: Some(decl::CounterDescriptor::default()),
+                static_or_dynamic_name: Some(decl::track_descriptor::StaticOrDynamicName::Name(name.to_string())),
+                parent_uuid: Some(proc_uuid),
+                uuid: Some(Self::new_uuid()),
+                ..Default::default()
+            }
+        }).uuid;
+
+        let counter_value = match value {
+            // Values above i64::MAX would wrap to negative ones
+            EventArg::Unsigned(v) => decl::track_event::CounterValueField::CounterValue(i64::try_from(v).unwrap_or(i64::MAX)),
+            EventArg::Signed(v) => decl::track_event::CounterValueField::CounterValue(v),
+            EventArg::Float(v) => decl::track_event::CounterValueField::DoubleCounterValue(v),
+        };
+
+        let track_event = decl::TrackEvent {
+            r#type: Some(decl::track_event::Type::Counter as i32),
+            track_uuid: uuid,
+            counter_value_field: Some(counter_value),
+            ..Default::default()
+        };
+
+        self.push_track_event(timestamp, track_event);
     }
+
+    /// Labels are shown in the process track details
+    pub fn add_process_labels(&mut self, labels: Vec<String>) {
+        if let Some(process) = self.proc_descriptor.process.as_mut() {
+            process.process_labels.extend(labels);
+        }
+    }
+
+    pub fn set_cmdline(&mut self, cmdline: Vec<String>) {
+        if let Some(process) = self.proc_descriptor.process.as_mut() {
+            process.cmdline = cmdline;
+        }
+    }
+
     pub fn set_thread_name(&mut self, thread_id: u64, thread_name: String) {
         self.thread_descriptors.entry(thread_id).or_insert_with(|| {
             let proc_uuid = self.proc_descriptor.uuid.unwrap();
@@ -120,6 +322,38 @@ impl PerfettoTraceFile {
             };
             self.trace.packet.push(thread_packet);
         }
+
+        for (_, counter_descriptor) in self.counter_descriptors.iter() {
+            let counter_packet = TracePacket {
+                data: Some(Data::TrackDescriptor(counter_descriptor.clone())),
+                ..Default::default()
+            };
+            self.trace.packet.push(counter_packet);
+        }
+
+        for (_, (task_descriptor, _)) in self.task_descriptors.iter() {
+            let task_packet = TracePacket {
+                data: Some(Data::TrackDescriptor(task_descriptor.clone())),
+                ..Default::default()
+            };
+            self.trace.packet.push(task_packet);
+        }
+
+        for (global_range_descriptor, _) in self.global_range_descriptors.values().flatten() {
+            let global_range_packet = TracePacket {
+                data: Some(Data::TrackDescriptor(global_range_descriptor.clone())),
+                ..Default::default()
+            };
+            self.trace.packet.push(global_range_packet);
+        }
+
+        if let Some(frames_descriptor) = &self.frames_descriptor {
+            let frames_packet = TracePacket {
+                data: Some(Data::TrackDescriptor(frames_descriptor.clone())),
+                ..Default::default()
+            };
+            self.trace.packet.push(frames_packet);
+        }
         self.trace.encode(&mut buf).unwrap();
         buf
     }
@@ -0,0 +1,7 @@
+use std::env;
+
+fn main() {
+    // Build information, saved to the encoder info packet
+    println!("cargo:rustc-env=SPARKLES_BUILD_TARGET={}", env::var("TARGET").unwrap());
+    println!("cargo:rustc-env=SPARKLES_BUILD_PROFILE={}", env::var("PROFILE").unwrap());
+}
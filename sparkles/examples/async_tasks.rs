@@ -0,0 +1,103 @@
+//! Async tasks example: polls of the same task happen on different threads
+//! 1. Run `cargo run --example async_tasks --release`
+//! 2. Parse result file: `cargo run --release --example interactive`
+//! 3. Go to https://ui.perfetto.dev/ and drag'n'drop generated `trace.perf` file
+//! 4. Each task has its own track with all its polls, while thread tracks show which worker polled it
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{mpsc, Arc, Mutex};
+use std::task::{Context, Poll, Wake, Waker};
+use std::thread;
+use std::time::Duration;
+use log::LevelFilter;
+use simple_logger::SimpleLogger;
+use sparkles_macro::instrument;
+
+#[sparkles::trace(args(step))]
+async fn load_chunk(step: u32) {
+    thread::sleep(Duration::from_micros(100));
+    YieldNow(false).await;
+    thread::sleep(Duration::from_micros(50));
+}
+
+async fn download(id: u32) {
+    for step in 0..5 {
+        load_chunk(step).await;
+    }
+    sparkles_macro::instant_event!("Download finished", id = id);
+}
+
+fn main() {
+    SimpleLogger::default().with_level(LevelFilter::Debug).init().unwrap();
+    let _finalize_guard = sparkles::init_default();
+
+    let (sender, receiver) = mpsc::channel::<Arc<Task>>();
+    for id in 0..4 {
+        let future = instrument!(download(id), "Download", id = id);
+        sender.send(Arc::new(Task {
+            future: Mutex::new(Some(Box::pin(future))),
+            sender: Mutex::new(sender.clone()),
+        })).unwrap();
+    }
+    drop(sender);
+
+    // Worker threads take tasks from the shared queue, so every poll may run on a different thread
+    let receiver = Arc::new(Mutex::new(receiver));
+    let workers: Vec<_> = (0..3).map(|i| {
+        let receiver = receiver.clone();
+        thread::Builder::new().name(format!("Worker {}", i)).spawn(move || {
+            loop {
+                let task = receiver.lock().unwrap().recv_timeout(Duration::from_millis(50));
+                let Ok(task) = task else {
+                    break;
+                };
+                task.poll();
+            }
+        }).unwrap()
+    }).collect();
+
+    for worker in workers {
+        worker.join().unwrap();
+    }
+}
+
+struct Task {
+    future: Mutex<Option<Pin<Box<dyn Future<Output = ()> + Send>>>>,
+    sender: Mutex<mpsc::Sender<Arc<Task>>>,
+}
+
+impl Task {
+    fn poll(self: Arc<Self>) {
+        let mut future = self.future.lock().unwrap();
+        if let Some(mut fut) = future.take() {
+            let waker = Waker::from(self.clone());
+            if fut.as_mut().poll(&mut Context::from_waker(&waker)).is_pending() {
+                *future = Some(fut);
+            }
+        }
+    }
+}
+
+impl Wake for Task {
+    fn wake(self: Arc<Self>) {
+        let sender = self.sender.lock().unwrap().clone();
+        let _ = sender.send(self);
+    }
+}
+
+/// Future, which returns `Pending` once
+struct YieldNow(bool);
+
+impl Future for YieldNow {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.0 {
+            return Poll::Ready(());
+        }
+        self.0 = true;
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
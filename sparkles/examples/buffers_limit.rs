@@ -0,0 +1,33 @@
+//! Thread-local buffers limit example: many threads record events faster than they can be flushed
+//! 1. Run `cargo run --example buffers_limit --release`
+//! 2. Parse result file: `cargo run --release --example interactive`. Lost time is reported for each thread
+//! 3. Go to https://ui.perfetto.dev/ and drag'n'drop generated `trace.perf` file. Lost events are marked with `Events lost` slices
+
+use std::thread;
+use log::LevelFilter;
+use simple_logger::SimpleLogger;
+use sparkles::config::SparklesConfig;
+use sparkles::BuffersLimitPolicy;
+use sparkles_macro::{instant_event, range_event_start};
+
+fn main() {
+    SimpleLogger::default().with_level(LevelFilter::Debug).init().unwrap();
+    // All thread-local buffers together may take at most 256KB.
+    // Events over the limit are dropped, use `BuffersLimitPolicy::Block` to keep all of them
+    let config = SparklesConfig::default()
+        .with_thread_flush_attempt_threshold(16 * 1024)
+        .with_thread_buffers_limit(256 * 1024, BuffersLimitPolicy::Drop);
+    let _finalize_guard = sparkles::init(config);
+
+    let handles: Vec<_> = (0..8).map(|_| thread::spawn(|| {
+        for _ in 0..100 {
+            let _g = range_event_start!("batch");
+            for _ in 0..10_000 {
+                instant_event!("✨");
+            }
+        }
+    })).collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+}
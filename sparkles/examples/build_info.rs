@@ -0,0 +1,27 @@
+//! Saving build information to the trace: crate version, git revision, profile, target and sparkles features
+//! 1. Run `cargo run --example build_info --release`
+//! 2. Parse result file: `cargo run --release --example interactive`
+//! 3. Go to https://ui.perfetto.dev/ and drag'n'drop generated `trace.perf` file, build info is shown in the process details
+//!
+//! Git revision is set by the build script of your crate. Add `sparkles-core` with `build` feature to `[build-dependencies]`
+//! and call `sparkles_core::build::emit_build_info()` from `build.rs`. This example has no such build script, so the revision is empty
+
+use log::LevelFilter;
+use simple_logger::SimpleLogger;
+use sparkles::config::SparklesConfig;
+use sparkles_macro::{instant_event, range_event_start};
+
+fn main() {
+    SimpleLogger::default().with_level(LevelFilter::Debug).init().unwrap();
+    let build_info = sparkles::build_info!();
+    log::info!("Build info: {:?}", build_info);
+
+    let config = SparklesConfig::default()
+        .with_build_info(build_info);
+    let _finalize_guard = sparkles::init(config);
+
+    let _g = range_event_start!("main()");
+    for _ in 0..1000 {
+        instant_event!("✨");
+    }
+}
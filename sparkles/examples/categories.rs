@@ -0,0 +1,34 @@
+//! Event categories example: detailed instrumentation is compiled in, but only selected subsystems are recorded
+//! 1. Run `cargo run --example categories --release`
+//! 2. Parse result file: `cargo run --release --example interactive`
+//! 3. Go to https://ui.perfetto.dev/ and drag'n'drop generated `trace.perf` file
+
+use std::thread;
+use std::time::Duration;
+use log::LevelFilter;
+use simple_logger::SimpleLogger;
+use sparkles_macro::{instant_event, range_event_start};
+
+fn main() {
+    SimpleLogger::default().with_level(LevelFilter::Debug).init().unwrap();
+    let _finalize_guard = sparkles::init_default();
+
+    // Only "net" events are recorded. Events without category are always recorded
+    sparkles::enable_only_categories(&["net"]);
+
+    for i in 0..100u32 {
+        let _g = range_event_start!("Main loop iteration");
+        if i == 50 {
+            // Categories can be switched at any moment
+            sparkles::enable_category("db");
+            sparkles::disable_category("net");
+        }
+
+        instant_event!("net", "Packet received", seq = i);
+        {
+            let _g = range_event_start!("db", "Query");
+            thread::sleep(Duration::from_micros(20));
+        }
+        instant_event!("db", "Cache miss");
+    }
+}
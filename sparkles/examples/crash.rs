@@ -0,0 +1,29 @@
+//! Saving the trace on panic or Ctrl-C: the finalize guard is not dropped in both cases
+//! 1. Run `cargo run --example crash --release`. Press Ctrl-C before the panic to stop it with SIGINT
+//! 2. Parse result file: `cargo run --release --example interactive`
+//! 3. Go to https://ui.perfetto.dev/ and drag'n'drop generated `trace.perf` file. Panic message is attached to the `panic` event
+
+use std::thread;
+use std::time::Duration;
+use log::LevelFilter;
+use simple_logger::SimpleLogger;
+use sparkles_macro::{instant_event, range_event_start};
+
+fn main() {
+    SimpleLogger::default().with_level(LevelFilter::Debug).init().unwrap();
+    let finalize_guard = sparkles::init_default();
+    sparkles::finalize_on_panic();
+    #[cfg(target_os = "linux")]
+    sparkles::finalize_on_signals();
+    // Trace is saved without the guard
+    finalize_guard.forget();
+
+    for i in 0..3000 {
+        let _g = range_event_start!("iteration");
+        instant_event!("✨");
+        thread::sleep(Duration::from_millis(1));
+        if i == 2999 {
+            panic!("Something went wrong on iteration {}", i);
+        }
+    }
+}
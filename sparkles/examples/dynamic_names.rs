@@ -0,0 +1,29 @@
+//! Runtime event names example
+//! 1. Run `cargo run --example dynamic_names --release`
+//! 2. Parse result file: `cargo run --release --example interactive`
+//! 3. Go to https://ui.perfetto.dev/ and drag'n'drop generated `trace.perf` file
+
+use std::thread;
+use std::time::Duration;
+use log::LevelFilter;
+use simple_logger::SimpleLogger;
+use sparkles_macro::{instant_event, range_event_end, range_event_start};
+
+fn main() {
+    SimpleLogger::default().with_level(LevelFilter::Debug).init().unwrap();
+    let _finalize_guard = sparkles::init_default();
+
+    let files = ["config.toml", "data.bin", "index.db"];
+    for (conn_id, file) in files.iter().enumerate() {
+        // Any expression, which is not a string literal, is used as a runtime name
+        let g = range_event_start!(format!("Connection #{}", conn_id));
+        instant_event!(file);
+        thread::sleep(Duration::from_micros(100));
+        range_event_end!(g, format!("{} loaded", file));
+    }
+
+    // Same can be done without macro
+    for i in 0..10 {
+        sparkles::instant_event_dyn(&format!("Iteration {}", i));
+    }
+}
@@ -0,0 +1,28 @@
+//! Event arguments example
+//! 1. Run `cargo run --example event_args --release`
+//! 2. Parse result file: `cargo run --release --example interactive`
+//! 3. Go to https://ui.perfetto.dev/ and drag'n'drop generated `trace.perf` file
+//! 4. Click on any event to see attached values in the "Arguments" section. Counter values are plotted on a separate track
+
+use std::collections::VecDeque;
+use log::LevelFilter;
+use simple_logger::SimpleLogger;
+use sparkles_macro::{counter, instant_event, range_event_start};
+
+fn main() {
+    SimpleLogger::default().with_level(LevelFilter::Debug).init().unwrap();
+    let _finalize_guard = sparkles::init_default();
+
+    let mut queue = VecDeque::new();
+    for i in 0..1_000u32 {
+        let packet_len = (i * 37) % 1500;
+        let _g = range_event_start!("Packet processing", size = packet_len, seq = i);
+        queue.push_back(packet_len);
+        if i % 3 == 0 {
+            queue.pop_front();
+        }
+        instant_event!("Queue updated", depth = queue.len(), load = queue.len() as f64 / 1000.0, delta = -1i32);
+        // Counters are displayed as a separate track with a plot
+        counter!("Queue length", queue.len());
+    }
+}
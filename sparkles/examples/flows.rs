@@ -0,0 +1,48 @@
+//! Flow events example: work items are passed between threads through channels
+//! 1. Run `cargo run --example flows --release`
+//! 2. Parse result file: `cargo run --release --example interactive`
+//! 3. Go to https://ui.perfetto.dev/ and drag'n'drop generated `trace.perf` file
+//! 4. Click on `Item received` event or `Decode` range to see arrows, connecting stages of the same work item
+
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+use log::LevelFilter;
+use simple_logger::SimpleLogger;
+use sparkles_macro::{flow_begin, flow_end, flow_step, range_event_start};
+
+fn main() {
+    SimpleLogger::default().with_level(LevelFilter::Debug).init().unwrap();
+    let _finalize_guard = sparkles::init_default();
+
+    let (decode_sender, decode_receiver) = mpsc::channel();
+    let (process_sender, process_receiver) = mpsc::channel();
+
+    let decoder = thread::Builder::new().name("Decoder".to_string()).spawn(move || {
+        for (item, flow) in decode_receiver {
+            let _g = range_event_start!("Decode");
+            flow_step!(flow, "Decoding", item = item);
+            thread::sleep(Duration::from_micros(100));
+            process_sender.send((item, flow)).unwrap();
+        }
+    }).unwrap();
+
+    let processor = thread::Builder::new().name("Processor".to_string()).spawn(move || {
+        for (item, flow) in process_receiver {
+            let _g = range_event_start!("Process");
+            thread::sleep(Duration::from_micros(150));
+            flow_end!(flow, "Processed", item = item);
+        }
+    }).unwrap();
+
+    for item in 0..100u32 {
+        // Flow id is passed along with the work item
+        let flow = flow_begin!("Item received", item = item);
+        decode_sender.send((item, flow)).unwrap();
+        thread::sleep(Duration::from_micros(120));
+    }
+    drop(decode_sender);
+
+    decoder.join().unwrap();
+    processor.join().unwrap();
+}
@@ -0,0 +1,49 @@
+//! Prefork server example: each forked worker process writes its own trace file
+//! 1. Run `cargo run --example fork --release` (Linux only)
+//! 2. Parse result files: `cargo run --release --example interactive`. There is one file per process
+//! 3. Go to https://ui.perfetto.dev/ and drag'n'drop generated `trace.perf` file
+
+use log::LevelFilter;
+use simple_logger::SimpleLogger;
+use sparkles_macro::{instant_event, range_event_start};
+
+#[cfg(target_os = "linux")]
+fn main() {
+    SimpleLogger::default().with_level(LevelFilter::Debug).init().unwrap();
+    let finalize_guard = sparkles::init_default();
+    {
+        let _g = range_event_start!("parent setup");
+        for _ in 0..1000 {
+            instant_event!("setup");
+        }
+    }
+
+    let mut children = Vec::new();
+    for _ in 0..3 {
+        // SAFETY: the child only records events and exits
+        let pid = unsafe { libc::fork() };
+        if pid == 0 {
+            // Child: new capture session with its own output file is started on the first event
+            {
+                let _g = range_event_start!("worker");
+                for _ in 0..100_000 {
+                    instant_event!("request");
+                }
+            }
+            drop(finalize_guard);
+            std::process::exit(0);
+        }
+        children.push(pid);
+    }
+
+    for pid in children {
+        // SAFETY: waiting for the own child process
+        unsafe { libc::waitpid(pid, std::ptr::null_mut(), 0) };
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn main() {
+    SimpleLogger::default().with_level(LevelFilter::Debug).init().unwrap();
+    log::warn!("Fork example is available only on Linux");
+}
@@ -0,0 +1,30 @@
+//! Frame markers example: game loop with a frame boundary on each iteration
+//! 1. Run `cargo run --example frames --release`
+//! 2. Parse result file: `cargo run --release --example interactive`. Frame duration statistics are printed
+//! 3. Go to https://ui.perfetto.dev/ and drag'n'drop generated `trace.perf` file. Frames are shown on the `Frames` track
+
+use std::thread;
+use std::time::Duration;
+use log::LevelFilter;
+use simple_logger::SimpleLogger;
+use sparkles_macro::range_event_start;
+
+fn main() {
+    SimpleLogger::default().with_level(LevelFilter::Debug).init().unwrap();
+    let _finalize_guard = sparkles::init_default();
+
+    for i in 0..120u64 {
+        sparkles::new_frame();
+        {
+            let _g = range_event_start!("Update");
+            thread::sleep(Duration::from_micros(200));
+        }
+        {
+            let _g = range_event_start!("Render");
+            // Every 30th frame is slow
+            let render_time = if i % 30 == 0 { 5000 } else { 800 };
+            thread::sleep(Duration::from_micros(render_time));
+        }
+    }
+    sparkles::new_frame();
+}
@@ -0,0 +1,54 @@
+//! Global ranges example: ranges are started on one thread and finished on another
+//! 1. Run `cargo run --example global_ranges --release`
+//! 2. Parse result file: `cargo run --release --example interactive`
+//! 3. Go to https://ui.perfetto.dev/ and drag'n'drop generated `trace.perf` file
+//! 4. Requests are displayed on the separate "Request" tracks, from accept to response write
+
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+use log::LevelFilter;
+use simple_logger::SimpleLogger;
+use sparkles_macro::{global_range_start, range_event_end, range_event_start};
+
+fn main() {
+    SimpleLogger::default().with_level(LevelFilter::Debug).init().unwrap();
+    let _finalize_guard = sparkles::init_default();
+
+    let (request_sender, request_receiver) = mpsc::channel();
+    let (response_sender, response_receiver) = mpsc::channel();
+
+    let acceptor = thread::Builder::new().name("Acceptor".to_string()).spawn(move || {
+        for id in 0..50u32 {
+            // Guard is `Send`, so it can travel with the request
+            let request = global_range_start!("Request", id = id);
+            request_sender.send((id, request)).unwrap();
+            thread::sleep(Duration::from_micros(200));
+        }
+    }).unwrap();
+
+    let handler = thread::Builder::new().name("Handler".to_string()).spawn(move || {
+        for (id, request) in request_receiver {
+            let _g = range_event_start!("Handle request");
+            thread::sleep(Duration::from_micros(300 + (id as u64 % 4) * 100));
+            response_sender.send(request).unwrap();
+        }
+    }).unwrap();
+
+    let writer = thread::Builder::new().name("Writer".to_string()).spawn(move || {
+        for (i, request) in response_receiver.into_iter().enumerate() {
+            thread::sleep(Duration::from_micros(50));
+            if i % 10 == 0 {
+                range_event_end!(request, "Failed");
+            }
+            else {
+                // Dropped guard finishes the range without additional name
+                drop(request);
+            }
+        }
+    }).unwrap();
+
+    acceptor.join().unwrap();
+    handler.join().unwrap();
+    writer.join().unwrap();
+}
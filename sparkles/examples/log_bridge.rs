@@ -0,0 +1,27 @@
+//! `log` integration example: log records are shown next to the ranges, in which they were emitted
+//! 1. Run `cargo run --example log_bridge --release`
+//! 2. Parse result file: `cargo run --release --example interactive`
+//! 3. Go to https://ui.perfetto.dev/ and drag'n'drop generated `trace.perf` file
+
+use std::thread;
+use std::time::Duration;
+use log::{info, warn, LevelFilter};
+use simple_logger::SimpleLogger;
+use sparkles::SparklesLogger;
+use sparkles_macro::range_event_start;
+
+fn main() {
+    SparklesLogger::new(SimpleLogger::new()).init(LevelFilter::Info).unwrap();
+    let _finalize_guard = sparkles::init_default();
+
+    for i in 0..20u32 {
+        let _g = range_event_start!("Request", id = i);
+        thread::sleep(Duration::from_micros(100));
+        if i % 7 == 0 {
+            warn!("Request {} is slow", i);
+        }
+        else {
+            info!("Request handled");
+        }
+    }
+}
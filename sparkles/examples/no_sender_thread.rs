@@ -0,0 +1,31 @@
+//! Running without background sender thread: events are sent by `sparkles::pump` from the application loop
+//! 1. Run `cargo run --example no_sender_thread --release`
+//! 2. Parse result file: `cargo run --release --example interactive`
+//! 3. Go to https://ui.perfetto.dev/ and drag'n'drop generated `trace.perf` file
+
+use std::thread;
+use std::time::Duration;
+use log::LevelFilter;
+use simple_logger::SimpleLogger;
+use sparkles::config::SparklesConfig;
+use sparkles_macro::{instant_event, range_event_start};
+
+fn main() {
+    SimpleLogger::default().with_level(LevelFilter::Debug).init().unwrap();
+    let config = SparklesConfig::default()
+        .without_sender_thread();
+    let _finalize_guard = sparkles::init(config);
+
+    for _ in 0..100 {
+        {
+            let _g = range_event_start!("tick");
+            for _ in 0..10_000 {
+                instant_event!("✨");
+            }
+            thread::sleep(Duration::from_millis(1));
+        }
+        // Move stored events to the trace file
+        sparkles::pump();
+    }
+    // Remaining events are sent on finalize
+}
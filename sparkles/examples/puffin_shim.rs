@@ -0,0 +1,34 @@
+//! puffin compatibility example: the same code as in `lib_puffin_test`, recorded by sparkles
+//! 1. Run `cargo run --example puffin_shim --release`
+//! 2. Parse result file: `cargo run --release --example interactive`
+//! 3. Go to https://ui.perfetto.dev/ and drag'n'drop generated `trace.perf` file
+
+use std::hint::black_box;
+use log::{info, LevelFilter};
+use simple_logger::SimpleLogger;
+use sparkles::puffin;
+
+const N: usize = 1_000;
+
+fn calc_sqrt(val: f64) -> f64 {
+    puffin::profile_function!();
+    val.sqrt()
+}
+
+fn main() {
+    SimpleLogger::default().with_level(LevelFilter::Debug).init().unwrap();
+    let _finalize_guard = sparkles::init_default();
+    puffin::set_scopes_on(true);
+
+    info!("Starting workload...");
+    for _ in 0..100 {
+        let mut v = 0.0f64;
+        for i in 0..N {
+            puffin::profile_scope!("sqrt calc");
+            v += calc_sqrt(i as f64 + 234.532);
+        }
+        puffin::GlobalProfiler::lock().new_frame();
+        black_box(v);
+    }
+    info!("Finished!");
+}
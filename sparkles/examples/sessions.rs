@@ -0,0 +1,39 @@
+//! Capture sessions example: capture is paused for a while, then finalized and started again, producing two trace files
+//! 1. Run `cargo run --example sessions --release`
+//! 2. Parse result file: `cargo run --release --example interactive`. The newest file is parsed
+//! 3. Go to https://ui.perfetto.dev/ and drag'n'drop generated `trace.perf` file
+
+use std::thread;
+use std::time::Duration;
+use log::LevelFilter;
+use simple_logger::SimpleLogger;
+use sparkles_macro::{instant_event, range_event_start};
+
+fn run_workload(session: u32) {
+    for i in 0..100u32 {
+        let _g = range_event_start!("Iteration", session = session);
+        if i == 30 {
+            // Nothing is recorded until `resume`
+            sparkles::pause();
+        }
+        if i == 60 {
+            sparkles::resume();
+        }
+        instant_event!("Step", i = i);
+        thread::sleep(Duration::from_micros(100));
+    }
+}
+
+fn main() {
+    SimpleLogger::default().with_level(LevelFilter::Debug).init().unwrap();
+
+    let finalize_guard = sparkles::init_default();
+    run_workload(1);
+    finalize_guard.early_drop();
+
+    // Events between sessions are not recorded
+    instant_event!("Lost event");
+
+    let _finalize_guard = sparkles::init_default();
+    run_workload(2);
+}
@@ -0,0 +1,84 @@
+//! Function instrumentation example
+//! 1. Run `cargo run --example trace_attribute --release`
+//! 2. Parse result file: `cargo run --release --example interactive`
+//! 3. Go to https://ui.perfetto.dev/ and drag'n'drop generated `trace.perf` file
+
+use std::future::Future;
+use std::pin::{pin, Pin};
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake};
+use std::thread::{self, Thread};
+use std::time::Duration;
+use log::LevelFilter;
+use simple_logger::SimpleLogger;
+
+#[sparkles::trace]
+fn checksum(data: &[u8]) -> u32 {
+    data.iter().map(|b| *b as u32).sum()
+}
+
+#[sparkles::trace(name = "Packet processing", args(seq, len))]
+fn process_packet(seq: u32, len: usize) -> u32 {
+    let data = vec![seq as u8; len];
+    checksum(&data)
+}
+
+/// Each poll of the returned future is recorded as a separate range
+#[sparkles::trace(args(id))]
+async fn load_resource(id: u32) -> u32 {
+    for _ in 0..3 {
+        thread::sleep(Duration::from_micros(50));
+        YieldNow(false).await;
+    }
+    id * 2
+}
+
+fn main() {
+    SimpleLogger::default().with_level(LevelFilter::Debug).init().unwrap();
+    let _finalize_guard = sparkles::init_default();
+
+    for seq in 0..100 {
+        process_packet(seq, (seq as usize * 37) % 1500);
+    }
+
+    for id in 0..10 {
+        block_on(load_resource(id));
+    }
+}
+
+/// Future, which returns `Pending` once
+struct YieldNow(bool);
+
+impl Future for YieldNow {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.0 {
+            return Poll::Ready(());
+        }
+        self.0 = true;
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
+
+struct ThreadWaker(Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+/// Minimal executor: poll the future on the current thread until it is ready
+fn block_on<F: Future>(fut: F) -> F::Output {
+    let mut fut = pin!(fut);
+    let waker = Arc::new(ThreadWaker(thread::current())).into();
+    let mut cx = Context::from_waker(&waker);
+    loop {
+        if let Poll::Ready(res) = fut.as_mut().poll(&mut cx) {
+            return res;
+        }
+        thread::park();
+    }
+}
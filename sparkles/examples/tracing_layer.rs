@@ -0,0 +1,39 @@
+//! `tracing` integration example: existing spans and events are recorded by sparkles
+//! 1. Run `cargo run --example tracing_layer --features tracing --release`
+//! 2. Parse result file: `cargo run --release --example interactive`
+//! 3. Go to https://ui.perfetto.dev/ and drag'n'drop generated `trace.perf` file
+
+use std::thread;
+use std::time::Duration;
+use log::LevelFilter;
+use simple_logger::SimpleLogger;
+use tracing::{info, info_span, instrument};
+use tracing_subscriber::prelude::*;
+
+#[instrument]
+fn process_item(id: u64, size: u32) {
+    thread::sleep(Duration::from_micros(50));
+    info!(bytes = size * 2, compressed = true, kind = "raw", "Item processed");
+}
+
+fn main() {
+    SimpleLogger::default().with_level(LevelFilter::Debug).init().unwrap();
+    let _finalize_guard = sparkles::init_default();
+
+    tracing_subscriber::registry().with(sparkles::SparklesLayer::new()).init();
+
+    let handles: Vec<_> = (0..2u64).map(|worker| {
+        thread::spawn(move || {
+            let span = info_span!("Worker", worker, processed = tracing::field::Empty);
+            let _e = span.enter();
+            for i in 0..100 {
+                process_item(worker * 100 + i, i as u32);
+            }
+            span.record("processed", 100);
+        })
+    }).collect();
+
+    for handle in handles {
+        handle.join().unwrap();
+    }
+}
@@ -0,0 +1,20 @@
+//! Event macros without the proc-macro dependency. Build sparkles with `default-features = false` to drop `sparkles-macro`
+//! 1. Run `cargo run --example without_proc_macro --release`
+//! 2. Parse result file: `cargo run --release --example interactive`
+//! 3. Go to https://ui.perfetto.dev/ and drag'n'drop generated `trace.perf` file
+
+use std::thread;
+use std::time::Duration;
+use log::LevelFilter;
+use simple_logger::SimpleLogger;
+
+fn main() {
+    SimpleLogger::default().with_level(LevelFilter::Debug).init().unwrap();
+    let _finalize_guard = sparkles::init_default();
+
+    for i in 0..100u32 {
+        let _g = sparkles::range_event_start!("iteration", index = i);
+        sparkles::instant_event!("✨");
+        thread::sleep(Duration::from_micros(100));
+    }
+}
@@ -0,0 +1,38 @@
+//! Build information, which is saved to the encoder info packet
+
+use sparkles_core::headers::BuildInfo;
+
+/// Features of the sparkles crate
+const FEATURES: &[(&str, bool)] = &[
+    ("self-tracing", cfg!(feature = "self-tracing")),
+    ("macro", cfg!(feature = "macro")),
+    ("off", cfg!(feature = "off")),
+    ("tracing", cfg!(feature = "tracing")),
+    ("accurate-timestamps-x86", cfg!(feature = "accurate-timestamps-x86")),
+];
+
+/// Profile, target and enabled features of the sparkles build
+pub fn sparkles_build_info() -> BuildInfo {
+    BuildInfo {
+        crate_name: None,
+        crate_version: None,
+        git_revision: None,
+        profile: env!("SPARKLES_BUILD_PROFILE").to_string(),
+        target: env!("SPARKLES_BUILD_TARGET").to_string(),
+        sparkles_features: FEATURES.iter()
+            .filter(|(_, enabled)| *enabled)
+            .map(|(name, _)| name.to_string())
+            .collect(),
+    }
+}
+
+/// Use `sparkles::build_info!()` instead
+#[doc(hidden)]
+pub fn crate_build_info(crate_name: &str, crate_version: &str, git_revision: Option<&str>) -> BuildInfo {
+    BuildInfo {
+        crate_name: Some(crate_name.to_string()),
+        crate_version: Some(crate_version.to_string()),
+        git_revision: git_revision.map(|s| s.to_string()),
+        ..sparkles_build_info()
+    }
+}
@@ -0,0 +1,91 @@
+//! Runtime filtering of events by category.
+//!
+//! Every category has a single global flag. Each call site caches reference to this flag on the first use,
+//! so the check of a disabled category is a single atomic load and doesn't touch the thread-local storage.
+
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+
+struct CategoryRegistry {
+    flags: BTreeMap<String, &'static AtomicBool>,
+    /// State for categories, which are not registered yet
+    enabled_by_default: bool,
+}
+
+static REGISTRY: Mutex<CategoryRegistry> = Mutex::new(CategoryRegistry {
+    flags: BTreeMap::new(),
+    enabled_by_default: true,
+});
+
+impl CategoryRegistry {
+    fn flag(&mut self, name: &str) -> &'static AtomicBool {
+        let enabled_by_default = self.enabled_by_default;
+        self.flags.entry(name.to_string()).or_insert_with(|| {
+            Box::leak(Box::new(AtomicBool::new(enabled_by_default)))
+        })
+    }
+}
+
+/// Category of the events at a single call site. Created by sparkles-macro when category is specified
+pub struct CategoryCallsite {
+    name: &'static str,
+    flag: OnceLock<&'static AtomicBool>,
+}
+
+impl CategoryCallsite {
+    pub const fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            flag: OnceLock::new(),
+        }
+    }
+
+    #[inline(always)]
+    pub fn is_enabled(&self) -> bool {
+        self.flag.get_or_init(|| REGISTRY.lock().unwrap().flag(self.name)).load(Ordering::Relaxed)
+    }
+}
+
+/// Enable or disable recording of events with the given category
+pub fn set_category_enabled(name: &str, enabled: bool) {
+    REGISTRY.lock().unwrap().flag(name).store(enabled, Ordering::Relaxed);
+}
+
+/// Enable recording of events with the given category
+pub fn enable_category(name: &str) {
+    set_category_enabled(name, true);
+}
+
+/// Disable recording of events with the given category
+pub fn disable_category(name: &str) {
+    set_category_enabled(name, false);
+}
+
+/// Check if events with the given category are recorded
+pub fn is_category_enabled(name: &str) -> bool {
+    REGISTRY.lock().unwrap().flag(name).load(Ordering::Relaxed)
+}
+
+/// Disable all categories except listed ones, including categories which are not used yet.
+///
+/// Events without category are not affected.
+pub fn enable_only_categories(names: &[&str]) {
+    let mut registry = REGISTRY.lock().unwrap();
+    registry.enabled_by_default = false;
+    for (name, flag) in registry.flags.iter() {
+        flag.store(names.contains(&name.as_str()), Ordering::Relaxed);
+    }
+    for name in names {
+        registry.flag(name).store(true, Ordering::Relaxed);
+    }
+}
+
+/// Enable all categories, including categories which are not used yet
+pub fn enable_all_categories() {
+    let mut registry = REGISTRY.lock().unwrap();
+    registry.enabled_by_default = true;
+    for flag in registry.flags.values() {
+        flag.store(true, Ordering::Relaxed);
+    }
+}
@@ -1,4 +1,5 @@
-use sparkles_core::config::LocalStorageConfig;
+use sparkles_core::config::{BuffersLimitPolicy, LocalStorageConfig};
+use sparkles_core::headers::BuildInfo;
 use crate::sender::file_sender::FileSenderConfig;
 use crate::sender::udp_sender::UdpSenderConfig;
 
@@ -33,6 +34,17 @@ pub struct SparklesConfig {
     /// Thread-local storage configuration
     pub local_storage_config: LocalStorageConfig,
 
+    /// Spawn background thread, which sends events from the global storage.
+    /// If disabled, `sparkles::pump` should be called periodically instead
+    ///
+    /// Default: true
+    pub sender_thread: bool,
+
+    /// Build information, saved to the trace
+    ///
+    /// Default: profile, target and features of sparkles. Use `sparkles::build_info!()` to add crate name, version and git revision
+    pub build_info: BuildInfo,
+
     pub file_sender_config: Option<FileSenderConfig>,
     pub udp_sender_config: Option<UdpSenderConfig>
 }
@@ -47,6 +59,8 @@ impl Default for SparklesConfig {
             cleanup_threshold: 0.9,
             cleanup_bottom_threshold: 0.7,
             local_storage_config: Default::default(),
+            sender_thread: true,
+            build_info: crate::build_info::sparkles_build_info(),
 
             file_sender_config: Some(Default::default()),
             udp_sender_config: None
@@ -91,6 +105,28 @@ impl SparklesConfig {
         self
     }
 
+    /// Limit total capacity of all thread-local buffers. `policy` is applied when the limit is reached
+    #[must_use]
+    pub fn with_thread_buffers_limit(mut self, limit: usize, policy: BuffersLimitPolicy) -> Self {
+        self.local_storage_config.total_buffers_limit = Some(limit);
+        self.local_storage_config.buffers_limit_policy = policy;
+        self
+    }
+
+    /// Do not spawn sender thread. Events are sent by `sparkles::pump` and on finalize
+    #[must_use]
+    pub fn without_sender_thread(mut self) -> Self {
+        self.sender_thread = false;
+        self
+    }
+
+    /// Save build information of the traced crate, usually `sparkles::build_info!()`
+    #[must_use]
+    pub fn with_build_info(mut self, build_info: BuildInfo) -> Self {
+        self.build_info = build_info;
+        self
+    }
+
     #[must_use]
     pub fn without_file_sender(mut self) -> Self {
         self.file_sender_config = None;
@@ -0,0 +1,239 @@
+//! Recording of events on the current thread, and the capture session control
+
+use std::sync::atomic::Ordering;
+use sparkles_core::local_storage::RangeStartRepr;
+use sparkles_core::local_storage::links::EventLink;
+use crate::config::SparklesConfig;
+use crate::global_storage::{self, finalize};
+use crate::thread_local_storage::{self, cur_session, CAPTURE_PAUSED, CAPTURE_STATE};
+use crate::{event_hash, EventArg, EventMeta};
+
+/// Use `sparkles-macro::instant_event!("name")` instead
+pub fn instant_event(hash: u32, meta: &'static EventMeta<'static>) {
+    thread_local_storage::with_active_tracer(|tracer| {
+        tracer.event_instant(hash, meta);
+    });
+}
+
+/// Use `sparkles-macro::instant_event!("name", arg = value)` instead
+pub fn instant_event_with_args(hash: u32, meta: &'static EventMeta<'static>, args: &[EventArg]) {
+    thread_local_storage::with_active_tracer(|tracer| {
+        tracer.event_instant_with_args(hash, meta, args);
+    });
+}
+
+/// Instant event with attached text message, which is shown next to the event arguments
+pub fn instant_event_with_message(hash: u32, meta: &'static EventMeta<'static>, args: &[EventArg], message: &str) {
+    thread_local_storage::with_active_tracer(|tracer| {
+        tracer.event_instant_with_message(hash, meta, args, message);
+    });
+}
+
+/// Use `sparkles-macro::counter!("name", value)` instead
+pub fn counter(hash: u32, meta: &'static EventMeta<'static>, value: EventArg) {
+    thread_local_storage::with_active_tracer(|tracer| {
+        tracer.event_counter(hash, meta, value);
+    });
+}
+
+/// Create instant event with a name, known only at runtime
+///
+/// Name is hashed on every call, so prefer `sparkles-macro::instant_event!("name")` for string literals.
+/// The same is done by `instant_event!(name)` with non-literal name.
+pub fn instant_event_dyn(name: &str) {
+    let hash = event_hash(name);
+    thread_local_storage::with_active_tracer(|tracer| {
+        tracer.event_instant(hash, &EventMeta::new(name));
+    });
+}
+
+/// Record a new value of the counter with a name, known only at runtime
+pub fn counter_dyn(name: &str, value: EventArg) {
+    let hash = event_hash(name);
+    thread_local_storage::with_active_tracer(|tracer| {
+        tracer.event_counter(hash, &EventMeta::new(name), value);
+    });
+}
+
+/// The value is created using macro `sparkles-macro::range_event_start!("name")`
+pub struct RangeStartGuard {
+    /// None if the range is already finished, or it was not recorded: category is disabled or capture is paused
+    repr: Option<RangeStartRepr>,
+    /// Capture session, in which range was started
+    session: u32,
+}
+
+impl RangeStartGuard {
+    /// Guard for the range with disabled category. Nothing is recorded on its end
+    pub fn disabled() -> Self {
+        Self {
+            repr: None,
+            session: 0,
+        }
+    }
+
+    fn new(repr: Option<RangeStartRepr>) -> Self {
+        Self {
+            repr,
+            session: cur_session(),
+        }
+    }
+
+    /// Take range start, if its capture session is still running
+    fn take_repr(&mut self) -> Option<RangeStartRepr> {
+        self.repr.take().filter(|_| self.session == cur_session())
+    }
+
+    /// Use `sparkles-macro::range_event_end!(guard, "name")` instead
+    pub fn end(mut self, hash: u32, meta: &'static EventMeta<'static>) {
+        if let Some(repr) = self.take_repr() {
+            thread_local_storage::with_running_tracer(|tracer| {
+                tracer.event_range_end(repr, hash, meta);
+            });
+        }
+    }
+
+    /// Finish range with a name, known only at runtime
+    pub fn end_dyn(mut self, name: &str) {
+        if let Some(repr) = self.take_repr() {
+            let hash = event_hash(name);
+            thread_local_storage::with_running_tracer(|tracer| {
+                tracer.event_range_end(repr, hash, &EventMeta::new(name));
+            });
+        }
+    }
+}
+
+#[cfg(feature = "tracing")]
+impl RangeStartGuard {
+    /// Finish range without name, if the thread-local storage is still alive. Used where the thread may be exiting
+    pub(crate) fn try_end(mut self) {
+        if let Some(repr) = self.take_repr() {
+            if thread_local_storage::is_session_running() {
+                thread_local_storage::try_with_thread_local_tracer(|tracer| {
+                    tracer.event_range_end(repr, 0, &EventMeta::new(""));
+                });
+            }
+        }
+    }
+}
+
+impl Drop for RangeStartGuard {
+    fn drop(&mut self) {
+        if let Some(repr) = self.take_repr() {
+            thread_local_storage::with_running_tracer(|tracer| {
+                tracer.event_range_end(repr, 0, &EventMeta::new(""));
+            });
+        }
+    }
+}
+
+/// Use `sparkles-macro::range_event_start!("name")` instead
+pub fn range_event_start(hash: u32, meta: &'static EventMeta<'static>) -> RangeStartGuard {
+    RangeStartGuard::new(thread_local_storage::with_active_tracer(|tracer| {
+        tracer.event_range_start(hash, meta)
+    }))
+}
+
+/// Use `sparkles-macro::range_event_start!("name", arg = value)` instead
+pub fn range_event_start_with_args(hash: u32, meta: &'static EventMeta<'static>, args: &[EventArg]) -> RangeStartGuard {
+    RangeStartGuard::new(thread_local_storage::with_active_tracer(|tracer| {
+        tracer.event_range_start_with_args(hash, meta, args)
+    }))
+}
+
+/// Start range event, linked with events on other threads
+pub(crate) fn range_event_start_with_link(hash: u32, meta: &'static EventMeta<'static>, args: &[EventArg], link: EventLink) -> RangeStartGuard {
+    RangeStartGuard::new(thread_local_storage::with_active_tracer(|tracer| {
+        tracer.event_range_start_with_link(hash, meta, args, link)
+    }))
+}
+
+/// Start range event with a name, known only at runtime
+///
+/// Name is hashed on every call, so prefer `sparkles-macro::range_event_start!("name")` for string literals.
+pub fn range_event_start_dyn(name: &str) -> RangeStartGuard {
+    let hash = event_hash(name);
+    RangeStartGuard::new(thread_local_storage::with_active_tracer(|tracer| {
+        tracer.event_range_start(hash, &EventMeta::new(name))
+    }))
+}
+
+/// Update current visible thread name. It will override the previous name when parsed
+pub fn set_cur_thread_name(name: String) {
+    thread_local_storage::with_thread_local_tracer(|tracer| {
+        tracer.set_cur_thread_name(name);
+    });
+}
+
+/// Manually flush all events from thread-local buffer to the global buffer
+pub fn flush_thread_local() {
+    thread_local_storage::with_thread_local_tracer(|tracer| {
+        tracer.flush(true);
+    });
+}
+
+/// Temporarily stop recording of events, without finishing the capture session.
+///
+/// Ranges, started before the pause, are still finished. Ranges, started while capture is paused, are not recorded.
+pub fn pause() {
+    CAPTURE_STATE.fetch_or(CAPTURE_PAUSED, Ordering::Relaxed);
+}
+
+/// Continue recording of events after `pause`
+pub fn resume() {
+    CAPTURE_STATE.fetch_and(!CAPTURE_PAUSED, Ordering::Relaxed);
+}
+
+/// Check if events are recorded right now: capture is not paused and not finalized
+pub fn is_capturing() -> bool {
+    CAPTURE_STATE.load(Ordering::Relaxed) == 0
+}
+
+/// Guard that will finalize global buffer when dropped
+/// Dropping this guard is equivalent to calling `sparkles::finalize`
+pub struct FinalizeGuard;
+
+impl FinalizeGuard {
+    pub fn early_drop(self) {}
+    pub fn forget(self) {
+        std::mem::forget(self)
+    }
+}
+
+impl Drop for FinalizeGuard {
+    fn drop(&mut self) {
+        finalize();
+    }
+}
+
+/// Init sparkles with the provided config
+///
+/// Returns a guard that will finalize global buffer when dropped
+///
+/// Can be called again after `finalize` to start a new capture session, which is written into a new file.
+///
+/// # Attention
+/// Do not forget to save finalize guard, returned from this call!
+/// If you don't need to use it, call `forget()`.
+#[must_use]
+pub fn init(config: SparklesConfig) -> FinalizeGuard {
+    // Init global storage
+    global_storage::start_session(config);
+
+    FinalizeGuard
+}
+
+/// Init sparkles with default config
+///
+/// Returns a guard that will finalize global buffer when dropped
+///
+/// # Attention
+/// Do not forget to save finalize guard, returned from this call!
+/// If you don't need to use it, call `forget()`.
+pub fn init_default() -> FinalizeGuard {
+    // Init global storage
+    global_storage::start_session(Default::default());
+
+    FinalizeGuard
+}
\ No newline at end of file
@@ -0,0 +1,47 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use sparkles_core::local_storage::id_mapping::EventType;
+use crate::{thread_local_storage, EventArg, EventMeta};
+
+static NEXT_FLOW_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Identifier of the flow, which links events on different threads with arrows.
+///
+/// Created by `sparkles-macro::flow_begin!("name")`. Can be copied and passed between threads along with the work item.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct FlowId(u64);
+
+impl FlowId {
+    /// Flow with disabled category, or started while capture is paused. Steps and end of such flow are not recorded
+    pub fn disabled() -> Self {
+        Self(0)
+    }
+}
+
+/// Use `sparkles-macro::flow_begin!("name")` instead
+pub fn flow_begin(hash: u32, meta: &'static EventMeta<'static>, args: &[EventArg]) -> FlowId {
+    let flow_id = NEXT_FLOW_ID.fetch_add(1, Ordering::Relaxed);
+    thread_local_storage::with_active_tracer(|tracer| {
+        tracer.event_flow(hash, meta, args, EventType::FlowBegin, flow_id);
+        FlowId(flow_id)
+    }).unwrap_or(FlowId::disabled())
+}
+
+/// Use `sparkles-macro::flow_step!(flow, "name")` instead
+pub fn flow_step(flow: FlowId, hash: u32, meta: &'static EventMeta<'static>, args: &[EventArg]) {
+    if flow == FlowId::disabled() {
+        return;
+    }
+    thread_local_storage::with_active_tracer(|tracer| {
+        tracer.event_flow(hash, meta, args, EventType::FlowStep, flow.0);
+    });
+}
+
+/// Use `sparkles-macro::flow_end!(flow, "name")` instead
+pub fn flow_end(flow: FlowId, hash: u32, meta: &'static EventMeta<'static>, args: &[EventArg]) {
+    if flow == FlowId::disabled() {
+        return;
+    }
+    thread_local_storage::with_active_tracer(|tracer| {
+        tracer.event_flow(hash, meta, args, EventType::FlowEnd, flow.0);
+    });
+}
@@ -0,0 +1,77 @@
+//! Fork handling: the child process starts a new capture session with its own sender, output file and pid.
+//!
+//! Locks are taken before the fork, so the child never inherits them in a locked state.
+//! Child handler only resets the state, the session is restarted on the first event or `pump` in the child,
+//! so children, which only call `exec`, don't create senders or files.
+
+use std::cell::RefCell;
+use std::path::Path;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{MutexGuard, Once, PoisonError};
+use sparkles_core::config::LocalStorageConfig;
+use crate::global_storage::{reset_in_forked_child, GlobalStorage, SendingTask, GLOBAL_STORAGE, SENDING_TASK};
+use crate::thread_local_storage::lock_local_storage_config;
+
+type ForkGuards = (MutexGuard<'static, Option<SendingTask>>, MutexGuard<'static, Option<GlobalStorage>>, MutexGuard<'static, LocalStorageConfig>);
+
+/// Pid of the process, recorded in the child handler
+static CHILD_PID: AtomicU32 = AtomicU32::new(0);
+
+thread_local! {
+    /// Locks, held by the forking thread during the fork
+    static FORK_GUARDS: RefCell<Option<ForkGuards>> = const { RefCell::new(None) };
+}
+
+/// Register fork handlers. Handlers can't be removed, so they are registered once per process
+pub(crate) fn register_fork_handlers() {
+    static REGISTERED: Once = Once::new();
+    REGISTERED.call_once(|| {
+        // SAFETY: handlers don't take arguments and don't unwind
+        unsafe {
+            libc::pthread_atfork(Some(prepare as unsafe extern "C" fn()), Some(parent as unsafe extern "C" fn()), Some(child as unsafe extern "C" fn()));
+        }
+    });
+}
+
+extern "C" fn prepare() {
+    let sending_task = SENDING_TASK.lock().unwrap_or_else(PoisonError::into_inner);
+    let global_storage = GLOBAL_STORAGE.lock().unwrap_or_else(PoisonError::into_inner);
+    let local_config = lock_local_storage_config();
+    FORK_GUARDS.set(Some((sending_task, global_storage, local_config)));
+}
+
+extern "C" fn parent() {
+    FORK_GUARDS.take();
+}
+
+extern "C" fn child() {
+    let Some((sending_task, global_storage, local_config)) = FORK_GUARDS.take() else {
+        return;
+    };
+    let session_running = global_storage.is_some();
+    drop(local_config);
+    drop(global_storage);
+    drop(sending_task);
+
+    if session_running {
+        // SAFETY: getpid is async-signal-safe
+        CHILD_PID.store(unsafe { libc::getpid() } as u32, Ordering::Relaxed);
+        reset_in_forked_child();
+    }
+}
+
+/// Pid of the forked child, recorded on fork
+pub(crate) fn child_pid() -> u32 {
+    CHILD_PID.load(Ordering::Relaxed)
+}
+
+/// Output file name for the child process: pid is appended to the file stem
+pub(crate) fn child_filename(filename: &str, pid: u32) -> String {
+    let path = Path::new(filename);
+    let stem = path.file_stem().map(|stem| stem.to_string_lossy()).unwrap_or_default();
+    let child_name = match path.extension() {
+        Some(extension) => format!("{}_{}.{}", stem, pid, extension.to_string_lossy()),
+        None => format!("{}_{}", stem, pid),
+    };
+    path.with_file_name(child_name).to_string_lossy().into_owned()
+}
@@ -0,0 +1,24 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use crate::{event_hash, thread_local_storage, EventMeta};
+
+static NEXT_FRAME_INDEX: AtomicU64 = AtomicU64::new(0);
+
+static FRAME_META: EventMeta<'static> = EventMeta {
+    name: "Frame",
+    arg_names: &["frame"],
+    location: None,
+    category: None,
+    is_static: true,
+};
+const FRAME_HASH: u32 = event_hash(FRAME_META.name);
+
+/// Mark the boundary of the frame: the previous frame ends and a new one starts.
+///
+/// Call it once per iteration of the game or render loop. Frames are shown on a dedicated track in the trace,
+/// and the parser reports frame duration statistics. Frame markers may be recorded on any thread.
+pub fn new_frame() {
+    let frame_index = NEXT_FRAME_INDEX.fetch_add(1, Ordering::Relaxed);
+    thread_local_storage::with_active_tracer(|tracer| {
+        tracer.event_frame(FRAME_HASH, &FRAME_META, frame_index);
+    });
+}
@@ -0,0 +1,87 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use crate::{event_hash, thread_local_storage, EventArg, EventMeta};
+use crate::thread_local_storage::cur_session;
+
+static NEXT_GLOBAL_RANGE_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Range, which can be started on one thread and finished on another.
+///
+/// The value is created using macro `sparkles-macro::global_range_start!("name")`.
+/// Finish it with `sparkles-macro::range_event_end!(guard, "name")`, or simply drop it on any thread.
+pub struct GlobalRangeGuard {
+    /// None if the range is already finished, or it was not recorded: category is disabled or capture is paused
+    range_id: Option<u64>,
+    /// Capture session, in which range was started
+    session: u32,
+}
+
+impl GlobalRangeGuard {
+    /// Guard for the global range with disabled category. Nothing is recorded on its end
+    pub fn disabled() -> Self {
+        Self {
+            range_id: None,
+            session: 0,
+        }
+    }
+
+    /// Take range id, if its capture session is still running
+    fn take_range_id(&mut self) -> Option<u64> {
+        self.range_id.take().filter(|_| self.session == cur_session())
+    }
+
+    /// Use `sparkles-macro::range_event_end!(guard, "name")` instead
+    pub fn end(mut self, hash: u32, meta: &'static EventMeta<'static>) {
+        if let Some(range_id) = self.take_range_id() {
+            thread_local_storage::with_running_tracer(|tracer| {
+                tracer.event_global_range_end(hash, meta, range_id);
+            });
+        }
+    }
+
+    /// Finish global range with a name, known only at runtime
+    pub fn end_dyn(mut self, name: &str) {
+        if let Some(range_id) = self.take_range_id() {
+            let hash = event_hash(name);
+            thread_local_storage::with_running_tracer(|tracer| {
+                tracer.event_global_range_end(hash, &EventMeta::new(name), range_id);
+            });
+        }
+    }
+}
+
+impl Drop for GlobalRangeGuard {
+    fn drop(&mut self) {
+        if let Some(range_id) = self.take_range_id() {
+            thread_local_storage::with_running_tracer(|tracer| {
+                tracer.event_global_range_end(0, &EventMeta::new(""), range_id);
+            });
+        }
+    }
+}
+
+fn start(hash: u32, meta: &EventMeta, args: &[EventArg]) -> GlobalRangeGuard {
+    let range_id = NEXT_GLOBAL_RANGE_ID.fetch_add(1, Ordering::Relaxed);
+    GlobalRangeGuard {
+        range_id: thread_local_storage::with_active_tracer(|tracer| {
+            tracer.event_global_range_start(hash, meta, args, range_id);
+            range_id
+        }),
+        session: cur_session(),
+    }
+}
+
+/// Use `sparkles-macro::global_range_start!("name")` instead
+pub fn global_range_start(hash: u32, meta: &'static EventMeta<'static>) -> GlobalRangeGuard {
+    start(hash, meta, &[])
+}
+
+/// Use `sparkles-macro::global_range_start!("name", arg = value)` instead
+pub fn global_range_start_with_args(hash: u32, meta: &'static EventMeta<'static>, args: &[EventArg]) -> GlobalRangeGuard {
+    start(hash, meta, args)
+}
+
+/// Start global range with a name, known only at runtime
+pub fn global_range_start_dyn(name: &str) -> GlobalRangeGuard {
+    let hash = event_hash(name);
+    start(hash, &EventMeta::new(name), &[])
+}
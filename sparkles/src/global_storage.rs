@@ -2,7 +2,7 @@
 //! All evens are being flushed into GLOBAL_STORAGE, and then head towards transport abstraction (UDP/TCP/file).
 
 use std::io::Read;
-use std::sync::Mutex;
+use std::sync::{Mutex, TryLockError};
 use std::{mem, thread};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::thread::{JoinHandle};
@@ -14,12 +14,21 @@ use sparkles_core::{Timestamp, TimestampProvider};
 use sparkles_core::sender::{ConfiguredSender, Sender, SenderChain};
 use crate::config::SparklesConfig;
 use crate::encoder::{send_data_bytes, send_encoder_info_packet, send_failed_page_headers, send_timestamp_freq};
-use crate::GLOBAL_FLUSHING_RUNNING;
+use crate::signals;
 use crate::sender::file_sender::FileSender;
-use crate::thread_local_storage::set_local_storage_config;
+use crate::thread_local_storage::{next_session, set_local_storage_config, CAPTURE_FINALIZED, CAPTURE_STATE};
+#[cfg(target_os = "linux")]
+use sparkles_core::local_storage::reset_total_buffers_capacity;
+#[cfg(target_os = "linux")]
+use crate::thread_local_storage::{try_with_thread_local_tracer, CAPTURE_FORKED};
 
 pub static GLOBAL_STORAGE: Mutex<Option<GlobalStorage>> = Mutex::new(None);
 static FINALIZE_STARTED: AtomicBool = AtomicBool::new(false);
+/// Set while events are taken from the global storage, thread-local storages don't flush at this time
+pub(crate) static GLOBAL_FLUSHING_RUNNING: AtomicBool = AtomicBool::new(false);
+/// Used instead of the sender thread, when `SparklesConfig::sender_thread` is disabled.
+/// Locked for the whole sending, so `finalize` waits for the running `pump`. Always locked before `GLOBAL_STORAGE`
+pub(crate) static SENDING_TASK: Mutex<Option<SendingTask>> = Mutex::new(None);
 
 pub struct GlobalStorage {
     config: SparklesConfig,
@@ -30,18 +39,18 @@ pub struct GlobalStorage {
 }
 
 impl GlobalStorage {
-    /// Create new global storage with given config and spawn sending thread
+    /// Create new global storage with given config and spawn sending thread, if it is enabled
     pub fn new(config: SparklesConfig) -> Self {
         // Set local storage config
         set_local_storage_config(config.local_storage_config);
 
-        let jh = spawn_sending_task(config.clone());
+        let sending_thread = config.sender_thread.then(|| spawn_sending_task(config.clone()));
 
         let global_capacity = config.global_capacity;
         Self {
             config,
             inner: ringbuf::LocalRb::new(global_capacity),
-            sending_thread: Some(jh),
+            sending_thread,
 
             skipped_msr_pages_headers: Vec::new(),
         }
@@ -80,6 +89,11 @@ impl GlobalStorage {
         }
     }
 
+    /// Called by thread local storage, when its events were dropped because of the buffers limit
+    pub fn push_lost(&mut self, header: &LocalPacketHeader) {
+        self.skipped_msr_pages_headers.push(header.clone());
+    }
+
     fn take_failed_pages(&mut self) -> Vec<LocalPacketHeader> {
         mem::take(&mut self.skipped_msr_pages_headers)
     }
@@ -107,10 +121,52 @@ impl GlobalStorage {
     }
 }
 
+pub(crate) const SENDER_THREAD_NAME: &str = "[Sparkles] Sender thread";
+
 fn spawn_sending_task(config: SparklesConfig) -> JoinHandle<()> {
-    thread::Builder::new().name("[Sparkles] Sender thread".to_string()).spawn(move || {
+    signals::sender_started();
+    thread::Builder::new().name(SENDER_THREAD_NAME.to_string()).spawn(move || {
         debug!("[sparkles] Flush thread started!");
 
+        let mut sending_task = SendingTask::new(&config);
+        loop {
+            signals::wait(Duration::from_millis(1));
+
+            // Read value before flushing
+            let pending_signal = signals::pending_signal();
+            let is_finalizing = FINALIZE_STARTED.load(Ordering::Relaxed) || pending_signal.is_some();
+            if is_finalizing {
+                debug!("[sparkles] Finalize detected!");
+                crate::flush_thread_local();
+            }
+
+            sending_task.send_stored(is_finalizing);
+            if let Some(signal) = pending_signal {
+                signals::terminate(signal);
+            }
+            if is_finalizing {
+                break;
+            }
+        }
+
+        debug!("[sparkles] Quit from flush thread!");
+        // Signal could arrive after the last check, the stream is already finished
+        if let Some(signal) = signals::sender_stopped() {
+            signals::terminate(signal);
+        }
+    }).unwrap()
+}
+
+/// Moves events from the global storage to the senders.
+/// Owned by the sender thread, or stored in `SENDING_TASK` when sparkles runs without sender thread
+pub(crate) struct SendingTask {
+    sender_chain: SenderChain,
+    freq_detector: TimestampFreqDetector,
+}
+
+impl SendingTask {
+    /// Create senders and send encoder info packet
+    fn new(config: &SparklesConfig) -> Self {
         let mut sender_chain = SenderChain::default();
         if let Some(file_sender_config) = config.file_sender_config.as_ref() {
             if let Some(sender) = FileSender::new(file_sender_config) {
@@ -132,81 +188,104 @@ fn spawn_sending_task(config: SparklesConfig) -> JoinHandle<()> {
         let process_name = std::env::current_exe().unwrap().file_name().unwrap().to_str().unwrap().to_string();
         let pid = std::process::id();
 
-        let mut freq_detector = TimestampFreqDetector::start(Duration::from_millis(100));
+        let freq_detector = TimestampFreqDetector::start(Duration::from_millis(100));
 
-        let info_header = SparklesEncoderInfo::new(process_name, pid);
+        let mut info_header = SparklesEncoderInfo::new(process_name, pid);
+        info_header.build_info = config.build_info.clone();
+        info_header.process_info = crate::process_info::collect_process_info();
         send_encoder_info_packet(&mut sender_chain, info_header);
 
-        loop {
-            thread::sleep(Duration::from_millis(1));
+        Self {
+            sender_chain,
+            freq_detector,
+        }
+    }
 
-            if let Some(ticks_per_sec) = freq_detector.next() {
-                send_timestamp_freq(&mut sender_chain, ticks_per_sec);
-            }
+    /// Send events from the global storage. On finalizing, all stored events are sent and the stream is finished
+    fn send_stored(&mut self, is_finalizing: bool) {
+        if let Some(ticks_per_sec) = self.freq_detector.next() {
+            send_timestamp_freq(&mut self.sender_chain, ticks_per_sec);
+        }
 
-            // Read value before flushing
-            let is_finalizing = FINALIZE_STARTED.load(Ordering::Relaxed);
-            if is_finalizing {
-                debug!("[sparkles] Finalize detected!");
-            }
+        // this thing should be fast
+        // Events are not recorded while global storage is locked: flushing them would lock it again
+        #[cfg(feature="self-tracing")]
+        let grd = crate::range_event_start_dyn("[internal] Taking stored events");
+        let (slices, failed_pages) = GLOBAL_STORAGE.lock().unwrap().as_mut().map(|global_storage| {
+            let failed_pages = global_storage.take_failed_pages();
+
+            GLOBAL_FLUSHING_RUNNING.store(true, Ordering::Relaxed);
+            (global_storage.try_take_buf(is_finalizing), failed_pages)
+        }).unwrap_or_default();
+        GLOBAL_FLUSHING_RUNNING.store(false, Ordering::Relaxed);
+        #[cfg(feature="self-tracing")]
+        drop(grd);
+
+        // handle buffers
+        if let Some((slice1, slice2)) = slices {
+            #[cfg(feature="self-tracing")]
+            let _grd = crate::range_event_start_dyn("[internal] Send data bytes");
+            send_data_bytes(&mut self.sender_chain, &slice1, &slice2);
+        }
 
-            // this thing should be fast
-            let (slices, failed_pages) = {
-                if is_finalizing {
-                    crate::flush_thread_local();
-                }
-
-                if let Some(global_storage) = GLOBAL_STORAGE.lock().unwrap().as_mut() {
-                    #[cfg(feature="self-tracing")]
-                    let grd = crate::range_event_start(crate::calculate_hash("[internal] Taking stored events"), "[internal] Taking stored events");
-                    let failed_pages = global_storage.take_failed_pages();
-                    
-                    GLOBAL_FLUSHING_RUNNING.store(true, Ordering::Relaxed);
-                    (global_storage.try_take_buf(is_finalizing), failed_pages)
-                }
-                else {
-                    (None, Vec::new())
-                }
-            };
-            GLOBAL_FLUSHING_RUNNING.store(false, Ordering::Relaxed);
-
-            // handle buffers
-            if let Some((slice1, slice2)) = slices {
-                #[cfg(feature="self-tracing")]
-                let grd = crate::range_event_start(crate::calculate_hash("[internal] Send data bytes"), "[internal] Send data bytes");
-                send_data_bytes(&mut sender_chain, &slice1, &slice2);
-            }
+        // handle failed pages
+        if !failed_pages.is_empty() {
+            trace!("Sending {} failed pages", failed_pages.len());
+            send_failed_page_headers(&mut self.sender_chain, &failed_pages)
+        }
 
-            // handle failed pages
-            if !failed_pages.is_empty() {
-                trace!("Sending {} failed pages", failed_pages.len());
-                send_failed_page_headers(&mut sender_chain, &failed_pages)
-            }
+        if is_finalizing {
+            let ticks_per_sec = self.freq_detector.next_forced();
+            send_timestamp_freq(&mut self.sender_chain, ticks_per_sec);
 
-            if is_finalizing {
-                let ticks_per_sec = freq_detector.next_forced();
-                send_timestamp_freq(&mut sender_chain, ticks_per_sec);
-                
-                debug!("[sparkles] Finalize in process...");
-                sender_chain.send(&[0xff]);
-                break;
-            }
+            debug!("[sparkles] Finalize in process...");
+            self.sender_chain.send(&[0xff]);
         }
+    }
+}
 
-        debug!("[sparkles] Quit from flush thread!");
-    }).unwrap()
+/// Send events from the global storage, when sparkles is configured without sender thread.
+///
+/// Call it periodically from the application loop, for example once per frame or tick.
+/// Events are sent only when the global storage is filled over `SparklesConfig::flush_threshold`,
+/// so the call is cheap most of the time. Does nothing if sender thread is used.
+pub fn pump() {
+    #[cfg(target_os = "linux")]
+    if CAPTURE_STATE.load(Ordering::Relaxed) & CAPTURE_FORKED != 0 {
+        restart_in_forked_child();
+    }
+    let mut sending_task = SENDING_TASK.lock().unwrap();
+    if let Some(task) = sending_task.as_mut() {
+        task.send_stored(false);
+    }
 }
 
-/// Blocking wait for global sending thread to finish its job
+/// Blocking wait for global sending thread to finish its job. Without sender thread, remaining events are sent from the current thread
+///
+/// Events are not recorded after this call, until the next session is started with `sparkles::init`
 pub fn finalize() {
     super::flush_thread_local();
+    finalize_session();
+}
 
+/// Send all stored events and close the session. Thread-local buffer of the current thread should be flushed before
+pub(crate) fn finalize_session() {
+    #[cfg(target_os = "linux")]
+    if CAPTURE_STATE.load(Ordering::Relaxed) & CAPTURE_FORKED != 0 {
+        // Session of the parent was not restarted in the forked child, nothing to send
+        let mut sending_task = SENDING_TASK.lock().unwrap();
+        let mut global_storage = GLOBAL_STORAGE.lock().unwrap();
+        leak_parent_session(&mut sending_task, &mut global_storage);
+        CAPTURE_STATE.fetch_and(!CAPTURE_FORKED, Ordering::Relaxed);
+    }
     FINALIZE_STARTED.store(true, Ordering::Relaxed);
-    let jh = if let Some(global_storage) = GLOBAL_STORAGE.lock().unwrap().as_mut() {
-        global_storage.take_jh()
-    } else {
-        None
-    };
+
+    // Without sender thread, the rest of the events is sent from the current thread, after the running `pump` is finished
+    if let Some(mut sending_task) = SENDING_TASK.lock().unwrap().take() {
+        sending_task.send_stored(true);
+    }
+
+    let jh = GLOBAL_STORAGE.lock().unwrap().as_mut().and_then(|global_storage| global_storage.take_jh());
 
     if let Some(jh) = jh {
         debug!("[sparkles] Joining sparkles flush thread...");
@@ -215,6 +294,103 @@ pub fn finalize() {
         });
     }
 
+    // Close the session. Events, which are still stored in thread-local buffers, will be dropped
+    let mut global_storage = GLOBAL_STORAGE.lock().unwrap();
+    CAPTURE_STATE.fetch_or(CAPTURE_FINALIZED, Ordering::Relaxed);
+    next_session();
+    *global_storage = None;
+    FINALIZE_STARTED.store(false, Ordering::Relaxed);
+    drop(global_storage);
+    signals::restore_prev_handlers();
+}
+
+/// Check if `SENDING_TASK` and `GLOBAL_STORAGE` can be locked within a short time. They are never available,
+/// if the current thread already holds one of them, or if they are poisoned
+pub(crate) fn session_locks_available() -> bool {
+    let deadline = Instant::now() + Duration::from_millis(100);
+    loop {
+        let sending_task = SENDING_TASK.try_lock();
+        let global_storage = GLOBAL_STORAGE.try_lock();
+        match (sending_task, global_storage) {
+            (Ok(_), Ok(_)) => return true,
+            (Err(TryLockError::Poisoned(_)), _) | (_, Err(TryLockError::Poisoned(_))) => return false,
+            _ => {}
+        }
+        if Instant::now() > deadline {
+            return false;
+        }
+        thread::yield_now();
+    }
+}
+
+/// Reset the state, inherited from the parent, in the forked child. Called from the fork handler,
+/// so only async-signal-safe operations are performed. Session is restarted later by `restart_in_forked_child`
+#[cfg(target_os = "linux")]
+pub(crate) fn reset_in_forked_child() {
+    FINALIZE_STARTED.store(false, Ordering::Relaxed);
+    GLOBAL_FLUSHING_RUNNING.store(false, Ordering::Relaxed);
+    // Sender thread of the parent does not exist in the child
+    let _ = signals::sender_stopped();
+    CAPTURE_STATE.fetch_or(CAPTURE_FORKED, Ordering::Relaxed);
+}
+
+/// Leak storage and senders of the parent session in the forked child: sender thread of the parent does not exist in the child,
+/// and senders share the output with the parent. Events, stored by the parent, are not sent by the child.
+/// Returns config of the parent session
+#[cfg(target_os = "linux")]
+fn leak_parent_session(sending_task: &mut Option<SendingTask>, global_storage: &mut Option<GlobalStorage>) -> Option<SparklesConfig> {
+    mem::forget(sending_task.take());
+    let parent_storage = global_storage.take()?;
+    let config = parent_storage.config.clone();
+    mem::forget(parent_storage);
+    Some(config)
+}
+
+/// Start a new session in the forked child with its own senders and output file.
+/// Called on the first event or `pump` after the fork
+#[cfg(target_os = "linux")]
+#[cold]
+pub(crate) fn restart_in_forked_child() {
+    let mut sending_task = SENDING_TASK.lock().unwrap();
+    let mut global_storage = GLOBAL_STORAGE.lock().unwrap();
+    if CAPTURE_STATE.load(Ordering::Relaxed) & CAPTURE_FORKED == 0 {
+        // Already restarted by another thread
+        return;
+    }
+    if let Some(mut config) = leak_parent_session(&mut sending_task, &mut global_storage) {
+        if let Some(filename) = config.file_sender_config.as_mut().and_then(|config| config.output_filename.as_mut()) {
+            *filename = crate::fork::child_filename(filename, crate::fork::child_pid());
+        }
+        // Thread-local events of the parent are dropped
+        next_session();
+        if !config.sender_thread {
+            *sending_task = Some(SendingTask::new(&config));
+        }
+        *global_storage = Some(GlobalStorage::new(config));
+    }
+    CAPTURE_STATE.fetch_and(!CAPTURE_FORKED, Ordering::Relaxed);
+    drop(global_storage);
+    drop(sending_task);
+
+    // Only the forking thread exists in the child: its storage is recreated, buffers of other threads are unreachable
+    try_with_thread_local_tracer(|_| {});
+    reset_total_buffers_capacity();
+}
+
+/// Start a new capture session, if there is no running one
+pub(crate) fn start_session(config: SparklesConfig) {
+    #[cfg(target_os = "linux")]
+    crate::fork::register_fork_handlers();
+
+    let mut sending_task = SENDING_TASK.lock().unwrap();
+    let mut global_storage = GLOBAL_STORAGE.lock().unwrap();
+    if global_storage.is_none() {
+        if !config.sender_thread {
+            *sending_task = Some(SendingTask::new(&config));
+        }
+        *global_storage = Some(GlobalStorage::new(config));
+    }
+    CAPTURE_STATE.fetch_and(!CAPTURE_FINALIZED, Ordering::Relaxed);
 }
 
 struct TimestampFreqDetector {
@@ -0,0 +1,69 @@
+use std::cell::Cell;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::task::{Context, Poll};
+use sparkles_core::local_storage::links::EventLink;
+use crate::{EventArg, EventMeta};
+
+static NEXT_TASK_ID: AtomicU64 = AtomicU64::new(1);
+
+thread_local! {
+    /// Task id of the future, which is being polled right now. 0 if none
+    static CUR_TASK_ID: Cell<u64> = const { Cell::new(0) };
+}
+
+/// Future wrapper, which records a range event for each poll of the inner future.
+///
+/// Polls can happen on any thread, all of them are linked by the task id and displayed on a separate task track.
+/// Instrumented futures, polled inside another instrumented future, share its task id.
+///
+/// Created by `sparkles-macro::instrument!(future, "name")` or `#[sparkles::trace]` on `async fn`.
+pub struct Instrumented<F> {
+    inner: F,
+    hash: u32,
+    meta: &'static EventMeta<'static>,
+    args: Vec<EventArg>,
+    task_id: u64,
+}
+
+impl<F: Future> Instrumented<F> {
+    /// Use `sparkles-macro::instrument!(future, "name")` instead
+    pub fn new(inner: F, hash: u32, meta: &'static EventMeta<'static>, args: Vec<EventArg>) -> Self {
+        Self {
+            inner,
+            hash,
+            meta,
+            args,
+            task_id: 0,
+        }
+    }
+}
+
+impl<F: Future> Future for Instrumented<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // SAFETY: `inner` is never moved out of the pinned wrapper
+        let this = unsafe { self.get_unchecked_mut() };
+        let inner = unsafe { Pin::new_unchecked(&mut this.inner) };
+
+        let parent_task_id = CUR_TASK_ID.get();
+        if this.task_id == 0 {
+            this.task_id = if parent_task_id != 0 {
+                parent_task_id
+            }
+            else {
+                NEXT_TASK_ID.fetch_add(1, Ordering::Relaxed)
+            };
+        }
+
+        CUR_TASK_ID.set(this.task_id);
+        let guard = crate::range_event_start_with_link(this.hash, this.meta, &this.args, EventLink::Task(this.task_id));
+        let res = inner.poll(cx);
+        drop(guard);
+        CUR_TASK_ID.set(parent_task_id);
+
+        res
+    }
+}
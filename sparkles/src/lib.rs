@@ -1,126 +1,72 @@
-mod thread_local_storage;
-mod global_storage;
 pub mod sender;
 pub mod config;
-mod encoder;
-
-use std::sync::atomic::AtomicBool;
-pub use global_storage::finalize;
-
-use sparkles_core::local_storage::RangeStartRepr;
-use crate::config::SparklesConfig;
-use crate::global_storage::GlobalStorage;
-
-static GLOBAL_FLUSHING_RUNNING: AtomicBool = AtomicBool::new(false);
-
-/// Use `sparkles-macro::instant_event!("name")` instead
-pub fn instant_event(hash: u32, string: &'static str) {
-    thread_local_storage::with_thread_local_tracer(|tracer| {
-        tracer.event_instant(hash, string);
-    });
-}
-
-/// The value is created using macro `sparkles-macro::range_event_start!("name")`
-pub struct RangeStartGuard {
-    repr: RangeStartRepr,
-    ended: bool,
-}
-
-impl RangeStartGuard {
-    /// Use `sparkles-macro::range_event_end!(guard, "name")` instead
-    pub fn end(mut self, hash: u32, string: &'static str) {
-        thread_local_storage::with_thread_local_tracer(|tracer| {
-            tracer.event_range_end(self.repr, hash, string);
-        });
-        self.ended = true;
-    }
-}
-
-impl Drop for RangeStartGuard {
-    fn drop(&mut self) {
-        if !self.ended {
-            thread_local_storage::with_thread_local_tracer(|tracer| {
-                tracer.event_range_end(self.repr, 0, "");
-            });
-        }
-    }
-}
-
-/// Use `sparkles-macro::range_event_start!("name")` instead
-pub fn range_event_start(hash: u32, string: &'static str) -> RangeStartGuard {
-    thread_local_storage::with_thread_local_tracer(|tracer| {
-        RangeStartGuard {
-            repr: tracer.event_range_start(hash, string),
-            ended: false,
-        }
-    })
-}
-
-/// Update current visible thread name. It will override the previous name when parsed
-pub fn set_cur_thread_name(name: String) {
-    thread_local_storage::with_thread_local_tracer(|tracer| {
-        tracer.set_cur_thread_name(name);
-    });
-}
+mod category;
+pub mod puffin;
+mod macros;
+mod build_info;
 
-/// Manually flush all events from thread-local buffer to the global buffer
-pub fn flush_thread_local() {
-    thread_local_storage::with_thread_local_tracer(|tracer| {
-        tracer.flush(true);
-    });
-}
-
-/// Guard that will finalize global buffer when dropped
-/// Dropping this guard is equivalent to calling `sparkles::finalize`
-pub struct FinalizeGuard;
-
-impl FinalizeGuard {
-    pub fn early_drop(self) {}
-    pub fn forget(self) {
-        std::mem::forget(self)
-    }
-}
-
-impl Drop for FinalizeGuard {
-    fn drop(&mut self) {
-        finalize();
-    }
-}
-
-/// Init sparkles with the provided config
-///
-/// Returns a guard that will finalize global buffer when dropped
-///
-/// # Attention
-/// Do not forget to save finalize guard, returned from this call!
-/// If you don't need to use it, call `forget()`.
-#[must_use]
-pub fn init(config: SparklesConfig) -> FinalizeGuard {
-    // Init global storage
-    global_storage::GLOBAL_STORAGE.lock().unwrap().get_or_insert_with(|| GlobalStorage::new(config));
-
-    FinalizeGuard
-}
-
-/// Init sparkles with default config
-///
-/// Returns a guard that will finalize global buffer when dropped
-///
-/// # Attention
-/// Do not forget to save finalize guard, returned from this call!
-/// If you don't need to use it, call `forget()`.
-pub fn init_default() -> FinalizeGuard {
-    // Init global storage
-    global_storage::GLOBAL_STORAGE.lock().unwrap().get_or_insert_with(|| GlobalStorage::new(Default::default()));
-
-    FinalizeGuard
-}
-
-pub(crate) fn calculate_hash(s: &str) -> u32 {
-    use std::collections::hash_map::DefaultHasher;
-    use std::hash::{Hash, Hasher};
-
-    let mut hasher = DefaultHasher::new();
-    s.hash(&mut hasher);
-    hasher.finish() as u32
-}
\ No newline at end of file
+// Capture runtime. With `off` feature it is replaced by no-op stubs with the same API
+#[cfg(not(feature = "off"))]
+mod thread_local_storage;
+#[cfg(not(feature = "off"))]
+mod global_storage;
+#[cfg(not(feature = "off"))]
+mod events;
+#[cfg(not(feature = "off"))]
+mod encoder;
+#[cfg(not(feature = "off"))]
+mod instrumented;
+#[cfg(not(feature = "off"))]
+mod global_range;
+#[cfg(not(feature = "off"))]
+mod flow;
+#[cfg(not(feature = "off"))]
+mod frame;
+#[cfg(not(feature = "off"))]
+mod log_bridge;
+#[cfg(not(feature = "off"))]
+mod panic_hook;
+#[cfg(not(feature = "off"))]
+mod signals;
+#[cfg(all(target_os = "linux", not(feature = "off")))]
+mod fork;
+#[cfg(not(feature = "off"))]
+mod process_info;
+#[cfg(all(feature = "tracing", not(feature = "off")))]
+mod tracing_layer;
+#[cfg(feature = "off")]
+mod off;
+
+pub use sparkles_core::local_storage::args::EventArg;
+pub use sparkles_core::hash::{event_hash, EventHasher};
+pub use sparkles_core::config::BuffersLimitPolicy;
+pub use sparkles_core::headers::BuildInfo;
+#[doc(hidden)]
+pub use build_info::crate_build_info as __crate_build_info;
+pub use sparkles_core::local_storage::id_mapping::{EventMeta, SourceLocation};
+pub use category::{set_category_enabled, enable_category, disable_category, is_category_enabled, enable_only_categories, enable_all_categories, CategoryCallsite};
+#[cfg(feature = "macro")]
+pub use sparkles_macro::trace;
+
+#[cfg(not(feature = "off"))]
+pub use global_storage::{finalize, pump};
+#[cfg(not(feature = "off"))]
+pub use events::*;
+#[cfg(not(feature = "off"))]
+pub use instrumented::Instrumented;
+#[cfg(not(feature = "off"))]
+pub use global_range::{global_range_start, global_range_start_with_args, global_range_start_dyn, GlobalRangeGuard};
+#[cfg(not(feature = "off"))]
+pub use flow::{flow_begin, flow_step, flow_end, FlowId};
+#[cfg(not(feature = "off"))]
+pub use frame::new_frame;
+#[cfg(not(feature = "off"))]
+pub use log_bridge::SparklesLogger;
+#[cfg(not(feature = "off"))]
+pub use panic_hook::finalize_on_panic;
+#[cfg(all(target_os = "linux", not(feature = "off")))]
+pub use signals::finalize_on_signals;
+#[cfg(all(feature = "tracing", not(feature = "off")))]
+pub use tracing_layer::SparklesLayer;
+#[cfg(feature = "off")]
+pub use off::*;
\ No newline at end of file
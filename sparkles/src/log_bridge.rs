@@ -0,0 +1,143 @@
+//! `log` integration: every log record, enabled by the wrapped logger, is recorded as an instant event, then passed to it.
+//!
+//! Event name is the record level and target, like `WARN my_app::net`. Formatted message is attached to the event.
+//! Event metadata is interned once per level, target and call site, keyed by the event hash.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use log::{LevelFilter, Log, Metadata, Record, SetLoggerError};
+use crate::{EventHasher, EventMeta, SourceLocation};
+
+type InternedEvent = (u32, &'static EventMeta<'static>);
+
+static INTERNED: Mutex<Option<HashMap<u32, InternedEvent>>> = Mutex::new(None);
+
+thread_local! {
+    static INTERNED_CACHE: RefCell<HashMap<u32, InternedEvent>> = RefCell::new(HashMap::new());
+}
+
+/// Event hash over the level, target and call site of the record
+fn record_hash(record: &Record) -> u32 {
+    EventHasher::new()
+        .str(record.level().as_str())
+        .str("\0").str(record.target())
+        .str("\0").str(record.module_path().unwrap_or(""))
+        .str("\0").str(record.file().unwrap_or(""))
+        .u32(record.line().unwrap_or(0))
+        .finish()
+}
+
+/// Location of the record, if it lives long enough to be recorded
+fn record_location(record: &Record) -> Option<SourceLocation> {
+    match (record.module_path_static(), record.file_static(), record.line()) {
+        (Some(module_path), Some(file), Some(line)) => Some(SourceLocation::new(module_path, file, line)),
+        _ => None,
+    }
+}
+
+/// Check that interned metadata describes the record, and not another one with the colliding hash
+fn is_interned_for(meta: &EventMeta, record: &Record) -> bool {
+    let name_matches = meta.name.strip_prefix(record.level().as_str())
+        .and_then(|rest| rest.strip_prefix(' '))
+        .is_some_and(|target| target == record.target());
+    let location_matches = match (&meta.location, record_location(record)) {
+        (Some(a), Some(b)) => a.module_path == b.module_path && a.file == b.file && a.line == b.line,
+        (None, None) => true,
+        _ => false,
+    };
+    name_matches && location_matches
+}
+
+/// Get event hash and metadata for the level, target and call site of the record
+fn intern(record: &Record) -> InternedEvent {
+    let mut hash = record_hash(record);
+    loop {
+        let cached = INTERNED_CACHE.with_borrow(|cache| cache.get(&hash).copied());
+        match cached {
+            Some((_, meta)) if is_interned_for(meta, record) => return (hash, meta),
+            // Hash of another record, try the next one
+            Some(_) => hash = EventHasher::new().u32(hash).finish(),
+            None => break,
+        }
+    }
+
+    let interned = {
+        let mut interned = INTERNED.lock().unwrap();
+        let interned = interned.get_or_insert_with(HashMap::new);
+        loop {
+            match interned.get(&hash) {
+                Some((_, meta)) if is_interned_for(meta, record) => break interned[&hash],
+                Some(_) => hash = EventHasher::new().u32(hash).finish(),
+                None => {
+                    let name = format!("{} {}", record.level(), record.target());
+                    let meta = Box::leak(Box::new(EventMeta {
+                        name: Box::leak(name.into_boxed_str()),
+                        arg_names: &[],
+                        location: record_location(record),
+                        category: None,
+                        is_static: true,
+                    }));
+                    interned.insert(hash, (hash, &*meta));
+                    break (hash, &*meta);
+                }
+            }
+        }
+    };
+
+    INTERNED_CACHE.with_borrow_mut(|cache| cache.insert(hash, interned));
+    interned
+}
+
+/// Records of sparkles itself may be emitted while thread-local storage is in use, so they are never recorded
+fn is_internal(record: &Record) -> bool {
+    let target = record.target();
+    target.strip_prefix("sparkles").is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+}
+
+/// `log::Log` implementation, which records log records as sparkles instant events and passes them to the wrapped logger.
+///
+/// ```rust,ignore
+/// let _finalize_guard = sparkles::init_default();
+/// sparkles::SparklesLogger::new(SimpleLogger::new()).init(LevelFilter::Debug).unwrap();
+/// ```
+pub struct SparklesLogger<L> {
+    inner: L,
+}
+
+impl<L: Log + 'static> SparklesLogger<L> {
+    pub fn new(inner: L) -> Self {
+        Self {
+            inner
+        }
+    }
+
+    /// Set this logger as the global logger with the given max level
+    pub fn init(self, max_level: LevelFilter) -> Result<(), SetLoggerError> {
+        log::set_logger(Box::leak(Box::new(self)))?;
+        log::set_max_level(max_level);
+        Ok(())
+    }
+}
+
+impl<L: Log> Log for SparklesLogger<L> {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.inner.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        // Message is formatted only for the records, which are actually recorded
+        if self.inner.enabled(record.metadata()) && crate::is_capturing() && !is_internal(record) {
+            let (hash, meta) = intern(record);
+            match record.args().as_str() {
+                Some(message) => crate::instant_event_with_message(hash, meta, &[], message),
+                None => crate::instant_event_with_message(hash, meta, &[], &record.args().to_string()),
+            }
+        }
+        self.inner.log(record);
+    }
+
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
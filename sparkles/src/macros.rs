@@ -0,0 +1,93 @@
+//! Event macros without the proc-macro dependency: `sparkles::instant_event!` and `sparkles::range_event_start!`.
+//!
+//! Only string literal names and numeric arguments are supported. Event hash and metadata are evaluated at compile time.
+//! Categories, runtime names and `#[trace]` are provided by `sparkles-macro`.
+
+/// Event hash and static metadata for the call site
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __event_meta {
+    ($name:literal $(, $arg:ident)*) => {{
+        const HASH: u32 = $crate::EventHasher::new()
+            .str($name)
+            $(.str("\0").str(stringify!($arg)))*
+            .str("\0").str(file!()).u32(line!()).u32(column!())
+            .finish();
+        static META: $crate::EventMeta<'static> = $crate::EventMeta {
+            name: $name,
+            arg_names: &[$(stringify!($arg)),*],
+            location: Some($crate::SourceLocation::new(module_path!(), file!(), line!())),
+            category: None,
+            is_static: true,
+        };
+        (HASH, &META)
+    }};
+}
+
+/// Create instant event with given name. Numeric values can be attached as `name = value` pairs
+///
+/// # Example
+/// ```rust,ignore
+/// sparkles::instant_event!("Packet received");
+/// sparkles::instant_event!("Packet received", size = packet.len());
+/// ```
+#[cfg(not(feature = "off"))]
+#[macro_export]
+macro_rules! instant_event {
+    ($name:literal $(, $arg:ident = $value:expr)* $(,)?) => {{
+        let (hash, meta) = $crate::__event_meta!($name $(, $arg)*);
+        $crate::instant_event_with_args(hash, meta, &[$($crate::EventArg::from($value)),*])
+    }};
+}
+
+/// Create range event with given name, which is finished when the guard is dropped.
+/// Numeric values can be attached to the range start as `name = value` pairs
+///
+/// # Example
+/// ```rust,ignore
+/// let _g = sparkles::range_event_start!("Packet parsing", size = packet.len());
+/// ```
+#[cfg(not(feature = "off"))]
+#[macro_export]
+macro_rules! range_event_start {
+    ($name:literal $(, $arg:ident = $value:expr)* $(,)?) => {{
+        let (hash, meta) = $crate::__event_meta!($name $(, $arg)*);
+        $crate::range_event_start_with_args(hash, meta, &[$($crate::EventArg::from($value)),*])
+    }};
+}
+
+#[cfg(feature = "off")]
+#[macro_export]
+macro_rules! instant_event {
+    ($name:literal $(, $arg:ident = $value:expr)* $(,)?) => {
+        if false {
+            let _ = ($(&$value,)*);
+        }
+    };
+}
+
+#[cfg(feature = "off")]
+#[macro_export]
+macro_rules! range_event_start {
+    ($name:literal $(, $arg:ident = $value:expr)* $(,)?) => {{
+        if false {
+            let _ = ($(&$value,)*);
+        }
+        $crate::RangeStartGuard::disabled()
+    }};
+}
+
+/// Build information of the calling crate: name, version and git revision.
+/// Git revision is available if `sparkles_core::build::emit_build_info()` is called from the build script
+///
+/// # Example
+/// ```rust,ignore
+/// let config = SparklesConfig::default()
+///     .with_build_info(sparkles::build_info!());
+/// ```
+#[macro_export]
+macro_rules! build_info {
+    () => {
+        $crate::__crate_build_info(env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"), option_env!("SPARKLES_GIT_REVISION"))
+    };
+}
@@ -0,0 +1,254 @@
+//! No-op replacement of the capture runtime, used with `off` feature.
+//!
+//! API is the same as with enabled capture, so the code compiles unchanged. Nothing is recorded,
+//! no threads or files are created, and the guards are zero-sized.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use crate::config::SparklesConfig;
+use crate::{EventArg, EventMeta};
+
+#[inline(always)]
+pub fn instant_event(_hash: u32, _meta: &'static EventMeta<'static>) {}
+
+#[inline(always)]
+pub fn instant_event_with_args(_hash: u32, _meta: &'static EventMeta<'static>, _args: &[EventArg]) {}
+
+#[inline(always)]
+pub fn instant_event_with_message(_hash: u32, _meta: &'static EventMeta<'static>, _args: &[EventArg], _message: &str) {}
+
+#[inline(always)]
+pub fn counter(_hash: u32, _meta: &'static EventMeta<'static>, _value: EventArg) {}
+
+#[inline(always)]
+pub fn instant_event_dyn(_name: &str) {}
+
+#[inline(always)]
+pub fn counter_dyn(_name: &str, _value: EventArg) {}
+
+/// Zero-sized range guard
+pub struct RangeStartGuard {
+    _private: (),
+}
+
+impl RangeStartGuard {
+    #[inline(always)]
+    pub fn disabled() -> Self {
+        Self {
+            _private: (),
+        }
+    }
+
+    #[inline(always)]
+    pub fn end(self, _hash: u32, _meta: &'static EventMeta<'static>) {}
+
+    #[inline(always)]
+    pub fn end_dyn(self, _name: &str) {}
+}
+
+impl Drop for RangeStartGuard {
+    #[inline(always)]
+    fn drop(&mut self) {}
+}
+
+#[inline(always)]
+pub fn range_event_start(_hash: u32, _meta: &'static EventMeta<'static>) -> RangeStartGuard {
+    RangeStartGuard::disabled()
+}
+
+#[inline(always)]
+pub fn range_event_start_with_args(_hash: u32, _meta: &'static EventMeta<'static>, _args: &[EventArg]) -> RangeStartGuard {
+    RangeStartGuard::disabled()
+}
+
+#[inline(always)]
+pub fn range_event_start_dyn(_name: &str) -> RangeStartGuard {
+    RangeStartGuard::disabled()
+}
+
+/// Zero-sized global range guard
+pub struct GlobalRangeGuard {
+    _private: (),
+}
+
+impl GlobalRangeGuard {
+    #[inline(always)]
+    pub fn disabled() -> Self {
+        Self {
+            _private: (),
+        }
+    }
+
+    #[inline(always)]
+    pub fn end(self, _hash: u32, _meta: &'static EventMeta<'static>) {}
+
+    #[inline(always)]
+    pub fn end_dyn(self, _name: &str) {}
+}
+
+impl Drop for GlobalRangeGuard {
+    #[inline(always)]
+    fn drop(&mut self) {}
+}
+
+#[inline(always)]
+pub fn global_range_start(_hash: u32, _meta: &'static EventMeta<'static>) -> GlobalRangeGuard {
+    GlobalRangeGuard::disabled()
+}
+
+#[inline(always)]
+pub fn global_range_start_with_args(_hash: u32, _meta: &'static EventMeta<'static>, _args: &[EventArg]) -> GlobalRangeGuard {
+    GlobalRangeGuard::disabled()
+}
+
+#[inline(always)]
+pub fn global_range_start_dyn(_name: &str) -> GlobalRangeGuard {
+    GlobalRangeGuard::disabled()
+}
+
+/// Zero-sized flow id
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct FlowId {
+    _private: (),
+}
+
+impl FlowId {
+    #[inline(always)]
+    pub fn disabled() -> Self {
+        Self {
+            _private: (),
+        }
+    }
+}
+
+#[inline(always)]
+pub fn flow_begin(_hash: u32, _meta: &'static EventMeta<'static>, _args: &[EventArg]) -> FlowId {
+    FlowId::disabled()
+}
+
+#[inline(always)]
+pub fn flow_step(_flow: FlowId, _hash: u32, _meta: &'static EventMeta<'static>, _args: &[EventArg]) {}
+
+#[inline(always)]
+pub fn flow_end(_flow: FlowId, _hash: u32, _meta: &'static EventMeta<'static>, _args: &[EventArg]) {}
+
+#[inline(always)]
+pub fn new_frame() {}
+
+/// Polls the inner future without recording anything
+pub struct Instrumented<F> {
+    inner: F,
+}
+
+impl<F: Future> Instrumented<F> {
+    #[inline(always)]
+    pub fn new(inner: F, _hash: u32, _meta: &'static EventMeta<'static>, _args: Vec<EventArg>) -> Self {
+        Self {
+            inner,
+        }
+    }
+}
+
+impl<F: Future> Future for Instrumented<F> {
+    type Output = F::Output;
+
+    #[inline(always)]
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // SAFETY: `inner` is never moved out of the pinned wrapper
+        unsafe { self.map_unchecked_mut(|this| &mut this.inner) }.poll(cx)
+    }
+}
+
+/// Passes log records to the wrapped logger
+pub struct SparklesLogger<L> {
+    inner: L,
+}
+
+impl<L: log::Log + 'static> SparklesLogger<L> {
+    pub fn new(inner: L) -> Self {
+        Self {
+            inner
+        }
+    }
+
+    pub fn init(self, max_level: log::LevelFilter) -> Result<(), log::SetLoggerError> {
+        log::set_logger(Box::leak(Box::new(self)))?;
+        log::set_max_level(max_level);
+        Ok(())
+    }
+}
+
+impl<L: log::Log> log::Log for SparklesLogger<L> {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        self.inner.enabled(metadata)
+    }
+
+    fn log(&self, record: &log::Record) {
+        self.inner.log(record);
+    }
+
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
+
+/// Layer without any effect
+#[cfg(feature = "tracing")]
+#[derive(Default)]
+pub struct SparklesLayer;
+
+#[cfg(feature = "tracing")]
+impl SparklesLayer {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+#[cfg(feature = "tracing")]
+impl<S: tracing_core::Subscriber> tracing_subscriber::Layer<S> for SparklesLayer {}
+
+pub fn set_cur_thread_name(_name: String) {}
+
+pub fn flush_thread_local() {}
+
+pub fn pause() {}
+
+pub fn resume() {}
+
+/// Always false: events are never recorded
+pub fn is_capturing() -> bool {
+    false
+}
+
+pub fn pump() {}
+
+pub fn finalize() {}
+
+pub fn finalize_on_panic() {}
+
+#[cfg(target_os = "linux")]
+pub fn finalize_on_signals() {}
+
+/// Zero-sized finalize guard
+pub struct FinalizeGuard;
+
+impl FinalizeGuard {
+    pub fn early_drop(self) {}
+    pub fn forget(self) {
+        std::mem::forget(self)
+    }
+}
+
+impl Drop for FinalizeGuard {
+    fn drop(&mut self) {}
+}
+
+#[must_use]
+pub fn init(_config: SparklesConfig) -> FinalizeGuard {
+    FinalizeGuard
+}
+
+pub fn init_default() -> FinalizeGuard {
+    FinalizeGuard
+}
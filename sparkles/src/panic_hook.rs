@@ -0,0 +1,57 @@
+//! Opt-in panic hook, which saves the trace before the panic unwinds or aborts the process.
+
+use std::panic::{self, PanicHookInfo};
+use std::sync::atomic::Ordering;
+use std::thread;
+use crate::global_storage::{finalize_session, session_locks_available, SENDER_THREAD_NAME};
+use crate::thread_local_storage::{try_with_thread_local_tracer, CAPTURE_STATE};
+use crate::{event_hash, EventMeta};
+
+static PANIC_META: EventMeta<'static> = EventMeta {
+    name: "panic",
+    arg_names: &[],
+    location: None,
+    category: None,
+    is_static: true,
+};
+const PANIC_HASH: u32 = event_hash(PANIC_META.name);
+
+/// Install panic hook, which records the panic message as an instant event and finalizes the trace, if the panic terminates the process.
+///
+/// Panic terminates the process with `panic = "abort"`, or when it unwinds the main thread. Then events from the panicking thread
+/// and the global storage are sent, and the stream is finished. Events, which are still stored in thread-local buffers of other threads, are lost.
+/// Panics on other threads may be caught, like in tokio tasks, so for them the event is only recorded and the capture continues.
+/// Nothing is saved, if the panic happens while sparkles holds its global locks on the panicking thread.
+/// Previously installed hook is called afterwards.
+pub fn finalize_on_panic() {
+    let prev_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        // Sender thread can't wait for itself to finish
+        if thread::current().name() != Some(SENDER_THREAD_NAME) {
+            save_trace(info);
+        }
+        prev_hook(info);
+    }));
+}
+
+fn save_trace(info: &PanicHookInfo) {
+    // Panic may happen while the global storage is locked on this thread, then flushing would never finish
+    if !session_locks_available() {
+        return;
+    }
+    // Panic may happen in the middle of recording an event, then the thread-local buffer is not available
+    try_with_thread_local_tracer(|tracer| {
+        if CAPTURE_STATE.load(Ordering::Relaxed) == 0 {
+            tracer.event_instant_with_message(PANIC_HASH, &PANIC_META, &[], &info.to_string());
+        }
+        tracer.flush(true);
+    });
+    if terminates_process() {
+        finalize_session();
+    }
+}
+
+/// Check if the panic terminates the process. Panics on other threads than main may be caught
+fn terminates_process() -> bool {
+    cfg!(panic = "abort") || thread::current().name() == Some("main")
+}
@@ -0,0 +1,75 @@
+//! Information about the traced process and the host, which is saved to the encoder info packet
+
+use std::time::{SystemTime, UNIX_EPOCH};
+use sparkles_core::headers::ProcessInfo;
+
+/// Collect process and host information at the capture session start
+pub fn collect_process_info() -> ProcessInfo {
+    ProcessInfo {
+        cmdline: std::env::args_os().map(|arg| arg.to_string_lossy().into_owned()).collect(),
+        hostname: hostname(),
+        os: std::env::consts::OS.to_string(),
+        os_version: os_version(),
+        kernel_version: kernel_version(),
+        cpu_model: cpu_model(),
+        logical_cores: std::thread::available_parallelism().map_or(0, |n| n.get() as u32),
+        start_time_unix_ns: SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_nanos() as u64),
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn read_trimmed(path: &str) -> Option<String> {
+    std::fs::read_to_string(path).ok()
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+}
+
+#[cfg(target_os = "linux")]
+fn hostname() -> Option<String> {
+    read_trimmed("/proc/sys/kernel/hostname")
+}
+
+#[cfg(not(target_os = "linux"))]
+fn hostname() -> Option<String> {
+    std::env::var("COMPUTERNAME").or_else(|_| std::env::var("HOSTNAME")).ok()
+}
+
+#[cfg(target_os = "linux")]
+fn os_version() -> Option<String> {
+    let os_release = std::fs::read_to_string("/etc/os-release").ok()?;
+    os_release.lines()
+        .find_map(|line| line.strip_prefix("PRETTY_NAME="))
+        .map(|name| name.trim_matches('"').to_string())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn os_version() -> Option<String> {
+    None
+}
+
+#[cfg(target_os = "linux")]
+fn kernel_version() -> Option<String> {
+    read_trimmed("/proc/sys/kernel/osrelease")
+}
+
+#[cfg(not(target_os = "linux"))]
+fn kernel_version() -> Option<String> {
+    None
+}
+
+#[cfg(target_os = "linux")]
+fn cpu_model() -> Option<String> {
+    let cpuinfo = std::fs::read_to_string("/proc/cpuinfo").ok()?;
+    // x86 has "model name", some ARM kernels report "Hardware" or "Model" instead
+    ["model name", "Hardware", "Model"].iter().find_map(|key| {
+        cpuinfo.lines().find_map(|line| {
+            let (name, value) = line.split_once(':')?;
+            (name.trim() == *key).then(|| value.trim().to_string())
+        })
+    })
+}
+
+#[cfg(not(target_os = "linux"))]
+fn cpu_model() -> Option<String> {
+    None
+}
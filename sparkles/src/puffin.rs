@@ -0,0 +1,168 @@
+//! puffin-compatible shim: `profile_function!`, `profile_scope!` and `GlobalProfiler`, backed by sparkles ranges.
+//!
+//! Code instrumented for puffin is switched to sparkles by replacing `use puffin;` with `use sparkles::puffin;`.
+//! Scope data (the optional second argument) is evaluated, but not recorded.
+//! Frames are recorded with `sparkles::new_frame`.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, MutexGuard, OnceLock};
+use crate::{event_hash, EventMeta, RangeStartGuard, SourceLocation};
+
+pub use crate::{profile_function, profile_scope};
+
+/// Switch of the shim scopes and frames, independent of the sparkles capture. Unlike puffin, scopes are on by default
+static SCOPES_ON: AtomicBool = AtomicBool::new(true);
+
+/// Call site of `profile_function!` or `profile_scope!`. Event metadata is created on the first use
+pub struct ScopeCallsite {
+    location: SourceLocation,
+    event: OnceLock<(u32, EventMeta<'static>)>,
+}
+
+impl ScopeCallsite {
+    pub const fn new(module_path: &'static str, file: &'static str, line: u32) -> Self {
+        Self {
+            location: SourceLocation::new(module_path, file, line),
+            event: OnceLock::new(),
+        }
+    }
+
+    /// Start range for this call site. `name` is called only once, so the first name is used for all calls
+    #[inline(always)]
+    pub fn start(&'static self, name: impl FnOnce() -> &'static str) -> RangeStartGuard {
+        if !are_scopes_on() {
+            return RangeStartGuard::disabled();
+        }
+        let (hash, meta) = self.event.get_or_init(|| {
+            let name = name();
+            let hash = event_hash(&format!("{}\0{}:{}", name, self.location.file, self.location.line));
+            let meta = EventMeta {
+                name,
+                arg_names: &[],
+                location: Some(self.location.clone()),
+                category: None,
+                is_static: true,
+            };
+            (hash, meta)
+        });
+        crate::range_event_start(*hash, meta)
+    }
+}
+
+/// Short function name from the type name of the nested function `f`, as used by `profile_function!`
+#[doc(hidden)]
+pub fn function_name(type_name: &'static str) -> &'static str {
+    let path = type_name.strip_suffix("::f").unwrap_or(type_name);
+    let path = path.trim_end_matches("::{{closure}}");
+    path.rsplit("::").next().unwrap_or(path)
+}
+
+/// Automatically name the profiling scope after the current function
+///
+/// # Example
+/// ```rust,ignore
+/// use sparkles::puffin;
+///
+/// fn update() {
+///     puffin::profile_function!();
+///     // Do some work
+/// }
+/// ```
+#[cfg(not(feature = "off"))]
+#[macro_export]
+macro_rules! profile_function {
+    () => {
+        $crate::profile_function!("")
+    };
+    ($data:expr) => {
+        let _sparkles_puffin_scope = {
+            fn f() {}
+            static SCOPE: $crate::puffin::ScopeCallsite = $crate::puffin::ScopeCallsite::new(module_path!(), file!(), line!());
+            let _ = &$data;
+            SCOPE.start(|| $crate::puffin::function_name(::core::any::type_name_of_val(&f)))
+        };
+    };
+}
+
+/// Record a range with the given name until the end of the current scope
+///
+/// Name must be `&'static str`, it is resolved only once per call site.
+///
+/// # Example
+/// ```rust,ignore
+/// use sparkles::puffin;
+///
+/// for item in items {
+///     puffin::profile_scope!("process item");
+///     // Do some work
+/// }
+/// ```
+#[cfg(not(feature = "off"))]
+#[macro_export]
+macro_rules! profile_scope {
+    ($name:expr) => {
+        $crate::profile_scope!($name, "")
+    };
+    ($name:expr, $data:expr) => {
+        let _sparkles_puffin_scope = {
+            static SCOPE: $crate::puffin::ScopeCallsite = $crate::puffin::ScopeCallsite::new(module_path!(), file!(), line!());
+            let _ = &$data;
+            SCOPE.start(|| $name)
+        };
+    };
+}
+
+#[cfg(feature = "off")]
+#[macro_export]
+macro_rules! profile_function {
+    () => {};
+    ($data:expr) => {
+        if false {
+            let _ = &$data;
+        }
+    };
+}
+
+#[cfg(feature = "off")]
+#[macro_export]
+macro_rules! profile_scope {
+    ($name:expr) => {
+        if false {
+            let _ = &$name;
+        }
+    };
+    ($name:expr, $data:expr) => {
+        if false {
+            let _ = (&$name, &$data);
+        }
+    };
+}
+
+/// Equivalent of `puffin::set_scopes_on`: turns shim scopes and frames on or off. Other sparkles events are not affected
+pub fn set_scopes_on(on: bool) {
+    SCOPES_ON.store(on, Ordering::Relaxed);
+}
+
+/// Equivalent of `puffin::are_scopes_on`
+#[inline(always)]
+pub fn are_scopes_on() -> bool {
+    SCOPES_ON.load(Ordering::Relaxed)
+}
+
+static GLOBAL_PROFILER: Mutex<GlobalProfiler> = Mutex::new(GlobalProfiler);
+
+/// Frame marker, replacing `puffin::GlobalProfiler`
+pub struct GlobalProfiler;
+
+impl GlobalProfiler {
+    pub fn lock() -> MutexGuard<'static, Self> {
+        GLOBAL_PROFILER.lock().unwrap()
+    }
+
+    /// Finish the current frame and start the next one
+    pub fn new_frame(&mut self) {
+        if are_scopes_on() {
+            crate::new_frame();
+        }
+    }
+}
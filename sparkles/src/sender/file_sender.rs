@@ -1,17 +1,20 @@
 use std::fs;
-use std::fs::File;
-use std::io::Write;
+use std::fs::{File, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
 use log::debug;
 use sparkles_core::sender::{ConfiguredSender, Sender};
 
+#[cfg_attr(feature = "off", allow(dead_code))]
 pub(crate) struct FileSender {
     file: File
 }
 
 #[derive(Debug, Default, Clone)]
 pub struct FileSenderConfig {
-    /// Output file name. Will overwrite file if already exists.
-    /// If not set, filename will be generated automatically in the format of `trace/%Y-%m-%d_%H-%M-%S.sprk`
+    /// Output file name.
+    /// If not set, filename will be generated automatically in the format of `trace/%Y-%m-%d_%H-%M-%S.sprk`.
+    /// Suffix `_N` is added to the file name if such file already exists.
     pub output_filename: Option<String>
 }
 impl Sender for FileSender {
@@ -25,7 +28,9 @@ impl ConfiguredSender for FileSender {
     fn new(cfg: &Self::Config) -> Option<Self> {
         // Create log file
         let res = if let Some(filename) = cfg.output_filename.clone() {
-            let file = File::create(filename).ok()?;
+            // Following capture sessions don't overwrite the file of the previous one
+            let (file, filename) = create_new_file(Path::new(&filename))?;
+            debug!("[sparkles] Created output file: {}", filename.display());
 
             Self {
                 file
@@ -39,9 +44,11 @@ impl ConfiguredSender for FileSender {
             }
 
             let now = chrono::Local::now();
-            let filename = format!("{}/{}.sprk", dir, now.format("%Y-%m-%d_%H-%M-%S"));
-            debug!("[sparkles] Creating output file: {}", filename);
-            let file = File::create(filename).ok()?;
+            let datetime = now.format("%Y-%m-%d_%H-%M-%S");
+            let filename = format!("{}/{}.sprk", dir, datetime);
+            // Several capture sessions or forked processes can be started within a single second
+            let (file, filename) = create_new_file(Path::new(&filename))?;
+            debug!("[sparkles] Created output file: {}", filename.display());
 
             Self {
                 file
@@ -50,4 +57,26 @@ impl ConfiguredSender for FileSender {
 
         Some(res)
     }
+}
+
+/// Create a new file. If it already exists, suffix `_N` is added to the file name
+fn create_new_file(path: &Path) -> Option<(File, PathBuf)> {
+    let mut candidate = path.to_path_buf();
+    let mut index = 1;
+    loop {
+        match OpenOptions::new().write(true).create_new(true).open(&candidate) {
+            Ok(file) => return Some((file, candidate)),
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                let mut name = path.file_stem()?.to_os_string();
+                name.push(format!("_{}", index));
+                if let Some(extension) = path.extension() {
+                    name.push(".");
+                    name.push(extension);
+                }
+                candidate = path.with_file_name(name);
+                index += 1;
+            }
+            Err(_) => return None,
+        }
+    }
 }
\ No newline at end of file
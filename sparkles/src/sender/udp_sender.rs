@@ -1,6 +1,7 @@
 use std::net::{Ipv4Addr, UdpSocket};
 use sparkles_core::sender::{ConfiguredSender, Sender};
 
+#[cfg_attr(feature = "off", allow(dead_code))]
 pub(crate) struct UdpSender {
     socket: UdpSocket,
     dst_addr: Option<(Ipv4Addr, u16)>
@@ -0,0 +1,145 @@
+//! Opt-in finalization on SIGINT and SIGTERM.
+//!
+//! Signal handler stores the signal number and wakes up the sender thread through an eventfd. Sender thread
+//! sends stored events with the end marker and terminates the process with the same signal.
+//! Without running sender thread, the handler terminates the process right away.
+
+use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+#[cfg(target_os = "linux")]
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Number of the received signal, zero if there is none
+static PENDING_SIGNAL: AtomicI32 = AtomicI32::new(0);
+/// Set while the sender thread is running, so the pending signal is handled by it
+static SENDER_RUNNING: AtomicBool = AtomicBool::new(false);
+/// eventfd, written by the signal handler to wake up the sender thread. -1 if it is not created
+static WAKEUP_FD: AtomicI32 = AtomicI32::new(-1);
+/// Set while the signal handlers are installed
+#[cfg(target_os = "linux")]
+static HANDLERS_INSTALLED: AtomicBool = AtomicBool::new(false);
+/// Actions of SIGINT and SIGTERM, which were set before the handlers are installed
+#[cfg(target_os = "linux")]
+static PREV_ACTIONS: Mutex<Vec<(libc::c_int, libc::sigaction)>> = Mutex::new(Vec::new());
+
+/// Finalize the trace when the process receives SIGINT or SIGTERM, then terminate it with the default action of the signal.
+///
+/// Stored events are sent by the sender thread right away. Without sender thread, or when there is no running capture session,
+/// the process is terminated immediately. Events, which are still stored in thread-local buffers, are lost.
+/// Second signal terminates the process immediately.
+///
+/// Previous handlers are restored on `finalize`, call it again for the next capture session.
+#[cfg(target_os = "linux")]
+pub fn finalize_on_signals() {
+    if WAKEUP_FD.load(Ordering::Relaxed) < 0 {
+        // SAFETY: plain syscall without pointers
+        let fd = unsafe { libc::eventfd(0, libc::EFD_NONBLOCK | libc::EFD_CLOEXEC) };
+        WAKEUP_FD.store(fd, Ordering::Relaxed);
+    }
+    let mut prev_actions = PREV_ACTIONS.lock().unwrap();
+    for signal in [libc::SIGINT, libc::SIGTERM] {
+        // SAFETY: handler only performs async-signal-safe operations
+        unsafe {
+            let mut action: libc::sigaction = std::mem::zeroed();
+            action.sa_sigaction = handle_signal as *const () as libc::sighandler_t;
+            libc::sigemptyset(&mut action.sa_mask);
+            action.sa_flags = libc::SA_RESTART;
+            let mut prev_action: libc::sigaction = std::mem::zeroed();
+            libc::sigaction(signal, &action, &mut prev_action);
+            // Repeated call would save own handler
+            if !HANDLERS_INSTALLED.load(Ordering::Relaxed) {
+                prev_actions.push((signal, prev_action));
+            }
+        }
+    }
+    HANDLERS_INSTALLED.store(true, Ordering::Relaxed);
+}
+
+#[cfg(target_os = "linux")]
+extern "C" fn handle_signal(signal: libc::c_int) {
+    if PENDING_SIGNAL.swap(signal, Ordering::SeqCst) != 0 || !SENDER_RUNNING.load(Ordering::SeqCst) {
+        terminate(signal);
+    }
+    let fd = WAKEUP_FD.load(Ordering::Relaxed);
+    if fd >= 0 {
+        let value = 1u64;
+        // SAFETY: `write` is async-signal-safe, value outlives the call
+        unsafe { libc::write(fd, &value as *const u64 as *const libc::c_void, std::mem::size_of::<u64>()) };
+    }
+}
+
+/// Restore actions of the signals, which were set before the handlers are installed
+pub(crate) fn restore_prev_handlers() {
+    #[cfg(target_os = "linux")]
+    {
+        let mut prev_actions = PREV_ACTIONS.lock().unwrap();
+        if HANDLERS_INSTALLED.swap(false, Ordering::Relaxed) {
+            for (signal, prev_action) in prev_actions.drain(..) {
+                // SAFETY: previous action was returned by the system
+                unsafe { libc::sigaction(signal, &prev_action, std::ptr::null_mut()) };
+            }
+        }
+    }
+}
+
+/// Mark the sender thread as running. Called before the thread is spawned
+pub(crate) fn sender_started() {
+    SENDER_RUNNING.store(true, Ordering::SeqCst);
+}
+
+/// Mark the sender thread as stopped. Signals, received after this call, are handled by the signal handler itself.
+/// Returns signal, which was received before
+pub(crate) fn sender_stopped() -> Option<i32> {
+    SENDER_RUNNING.store(false, Ordering::SeqCst);
+    pending_signal()
+}
+
+/// Sleep of the sender thread between sends. Interrupted by the received signal
+pub(crate) fn wait(timeout: Duration) {
+    #[cfg(target_os = "linux")]
+    {
+        let fd = WAKEUP_FD.load(Ordering::Relaxed);
+        if fd >= 0 {
+            let mut poll_fd = libc::pollfd {
+                fd,
+                events: libc::POLLIN,
+                revents: 0,
+            };
+            // SAFETY: pointers are valid for the duration of the calls
+            unsafe {
+                if libc::poll(&mut poll_fd, 1, timeout.as_millis() as libc::c_int) > 0 {
+                    let mut value = 0u64;
+                    libc::read(fd, &mut value as *mut u64 as *mut libc::c_void, std::mem::size_of::<u64>());
+                }
+            }
+            return;
+        }
+    }
+    std::thread::sleep(timeout);
+}
+
+/// Signal, which is waiting for the trace to be finalized
+pub(crate) fn pending_signal() -> Option<i32> {
+    match PENDING_SIGNAL.load(Ordering::SeqCst) {
+        0 => None,
+        signal => Some(signal),
+    }
+}
+
+/// Terminate the process with the default action of the signal
+pub(crate) fn terminate(signal: i32) -> ! {
+    #[cfg(target_os = "linux")]
+    // SAFETY: only async-signal-safe functions are called
+    unsafe {
+        libc::signal(signal, libc::SIG_DFL);
+        // Signal is blocked while its handler runs
+        let mut set: libc::sigset_t = std::mem::zeroed();
+        libc::sigemptyset(&mut set);
+        libc::sigaddset(&mut set, signal);
+        libc::sigprocmask(libc::SIG_UNBLOCK, &set, std::ptr::null_mut());
+        libc::raise(signal);
+        libc::_exit(128 + signal)
+    }
+    #[cfg(not(target_os = "linux"))]
+    std::process::exit(128 + signal)
+}
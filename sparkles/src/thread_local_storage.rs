@@ -1,29 +1,70 @@
 use std::cell::RefCell;
-use std::sync::atomic::Ordering;
-use std::sync::OnceLock;
+use std::sync::atomic::{AtomicU32, AtomicU8, Ordering};
+use std::sync::{Mutex, MutexGuard, PoisonError};
 use std::thread;
 use sparkles_core::config::LocalStorageConfig;
 use sparkles_core::headers::{LocalPacketHeader, ThreadInfo};
 use sparkles_core::local_storage::{GlobalStorageImpl, LocalStorage};
-use crate::GLOBAL_FLUSHING_RUNNING;
-use crate::global_storage::{GlobalStorage, GLOBAL_STORAGE};
+use crate::global_storage::{GlobalStorage, GLOBAL_FLUSHING_RUNNING, GLOBAL_STORAGE};
 
-pub struct GlobalStorageRef;
+/// Reference to the global storage of the capture session, in which thread-local storage was created.
+/// Events of the finished sessions are discarded on flush.
+pub struct GlobalStorageRef {
+    session: u32,
+}
 pub type ThreadLocalStorage = LocalStorage<GlobalStorageRef>;
 
-static LOCAL_CONFIG: OnceLock<LocalStorageConfig> = OnceLock::new();
+static LOCAL_CONFIG: Mutex<LocalStorageConfig> = Mutex::new(LocalStorageConfig::default());
 pub(crate) fn set_local_storage_config(config: LocalStorageConfig) {
-    let _ = LOCAL_CONFIG.set(config);
+    *LOCAL_CONFIG.lock().unwrap() = config;
+}
+/// Lock thread-local storage config, so it can't be changed during fork
+pub(crate) fn lock_local_storage_config() -> MutexGuard<'static, LocalStorageConfig> {
+    LOCAL_CONFIG.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+pub(crate) const CAPTURE_PAUSED: u8 = 1;
+pub(crate) const CAPTURE_FINALIZED: u8 = 2;
+/// Set in the forked child, until the session of the parent is restarted
+#[cfg(target_os = "linux")]
+pub(crate) const CAPTURE_FORKED: u8 = 4;
+/// Bit set of the reasons, why events are not recorded right now. Zero when capture is active
+pub(crate) static CAPTURE_STATE: AtomicU8 = AtomicU8::new(0);
+/// Incremented on each `finalize`
+static SESSION_ID: AtomicU32 = AtomicU32::new(0);
+
+/// Id of the current capture session
+#[inline(always)]
+pub(crate) fn cur_session() -> u32 {
+    SESSION_ID.load(Ordering::Relaxed)
+}
+
+/// Start a new capture session. Thread-local storages of the previous session will be recreated on the next access
+pub(crate) fn next_session() {
+    SESSION_ID.fetch_add(1, Ordering::Relaxed);
+}
+
+impl GlobalStorageRef {
+    /// Check if data still belongs to the running session. Must be called with locked `GLOBAL_STORAGE`
+    fn is_outdated(&self) -> bool {
+        self.session != cur_session() || CAPTURE_STATE.load(Ordering::Relaxed) & CAPTURE_FINALIZED != 0
+    }
 }
 
 impl GlobalStorageImpl for GlobalStorageRef {
     fn flush(&self, header: &LocalPacketHeader, data: &[u8]) {
         let mut global_storage_ref = GLOBAL_STORAGE.lock().unwrap();
+        if self.is_outdated() {
+            return;
+        }
         let global_storage_ref = global_storage_ref.get_or_insert_with(|| GlobalStorage::new(Default::default()));
         global_storage_ref.push_buf(header, data);
     }
     fn try_flush(&self, header: &LocalPacketHeader, data: &[u8]) -> bool {
         if let Ok(mut global_storage_ref) = GLOBAL_STORAGE.try_lock() {
+            if self.is_outdated() {
+                return true;
+            }
             let global_storage_ref = global_storage_ref.get_or_insert_with(|| GlobalStorage::new(Default::default()));
             global_storage_ref.push_buf(header, data);
             true
@@ -35,9 +76,17 @@ impl GlobalStorageImpl for GlobalStorageRef {
     fn is_buf_available(&self) -> bool {
         !GLOBAL_FLUSHING_RUNNING.load(Ordering::Relaxed)
     }
+    fn report_lost(&self, header: &LocalPacketHeader) {
+        let mut global_storage_ref = GLOBAL_STORAGE.lock().unwrap();
+        if self.is_outdated() {
+            return;
+        }
+        let global_storage_ref = global_storage_ref.get_or_insert_with(|| GlobalStorage::new(Default::default()));
+        global_storage_ref.push_lost(header);
+    }
 }
 
-fn new_local_storage() -> LocalStorage<GlobalStorageRef> {
+fn new_local_storage(session: u32) -> LocalStorage<GlobalStorageRef> {
     let thread_info = thread::current();
     let thread_name = thread_info.name().unwrap_or("Unnamed thread").to_string();
     let thread_id = thread_id::get() as u64;
@@ -45,18 +94,79 @@ fn new_local_storage() -> LocalStorage<GlobalStorageRef> {
         new_thread_name: Some(thread_name.clone()),
         thread_id,
     };
-    let config = *LOCAL_CONFIG.get_or_init(LocalStorageConfig::default);
-    LocalStorage::new(GlobalStorageRef, Some(thread_info), config)
+    let config = *LOCAL_CONFIG.lock().unwrap();
+    LocalStorage::new(GlobalStorageRef { session }, Some(thread_info), config)
 }
 
+thread_local! {
+    static TRACER: RefCell<(u32, ThreadLocalStorage)> = RefCell::new((cur_session(), new_local_storage(cur_session())));
+}
+
+/// Recreate thread-local storage, if it belongs to the finished session
 #[inline(always)]
-pub fn with_thread_local_tracer<F, R>(f: F) -> R
-where F: FnOnce(&mut ThreadLocalStorage) -> R {
-    thread_local! {
-        static TRACER: RefCell<ThreadLocalStorage> = RefCell::new(new_local_storage());
+fn actual_tracer(session: &mut u32, tracer: &mut ThreadLocalStorage) {
+    let cur_session = cur_session();
+    if *session != cur_session {
+        // Events of the finished session are dropped together with the old storage
+        *tracer = new_local_storage(cur_session);
+        *session = cur_session;
     }
+}
 
-    TRACER.with_borrow_mut(|tracer| {
+#[inline(always)]
+pub fn with_thread_local_tracer<F, R>(f: F) -> R
+where F: FnOnce(&mut ThreadLocalStorage) -> R {
+    TRACER.with_borrow_mut(|(session, tracer)| {
+        actual_tracer(session, tracer);
         f(tracer)
     })
+}
+
+/// Same as `with_thread_local_tracer`, but does nothing if the tracer is already borrowed on this thread or destroyed.
+/// Used where the caller may be inside of another tracer call, like in the panic hook
+pub(crate) fn try_with_thread_local_tracer<F, R>(f: F) -> Option<R>
+where F: FnOnce(&mut ThreadLocalStorage) -> R {
+    TRACER.try_with(|tracer| {
+        let mut tracer = tracer.try_borrow_mut().ok()?;
+        let (session, tracer) = &mut *tracer;
+        actual_tracer(session, tracer);
+        Some(f(tracer))
+    }).ok().flatten()
+}
+
+/// Same as `with_thread_local_tracer`, but does nothing if capture is paused or finalized
+#[inline(always)]
+pub fn with_active_tracer<F, R>(f: F) -> Option<R>
+where F: FnOnce(&mut ThreadLocalStorage) -> R {
+    if CAPTURE_STATE.load(Ordering::Relaxed) != 0 && !restart_if_forked() {
+        return None;
+    }
+    Some(with_thread_local_tracer(f))
+}
+
+/// Same as `with_thread_local_tracer`, but does nothing if capture is finalized. Unlike `with_active_tracer`,
+/// records while capture is paused, so ranges, started before the pause, are finished
+#[inline(always)]
+pub(crate) fn with_running_tracer<F, R>(f: F) -> Option<R>
+where F: FnOnce(&mut ThreadLocalStorage) -> R {
+    if !is_session_running() {
+        return None;
+    }
+    Some(with_thread_local_tracer(f))
+}
+
+/// Check if the capture session is running, it may be paused
+#[inline(always)]
+pub(crate) fn is_session_running() -> bool {
+    CAPTURE_STATE.load(Ordering::Relaxed) & !CAPTURE_PAUSED == 0
+}
+
+/// Restart the session in the forked child on its first event. Returns true if capture is active after it
+#[cold]
+fn restart_if_forked() -> bool {
+    #[cfg(target_os = "linux")]
+    if CAPTURE_STATE.load(Ordering::Relaxed) == CAPTURE_FORKED {
+        crate::global_storage::restart_in_forked_child();
+    }
+    CAPTURE_STATE.load(Ordering::Relaxed) == 0
 }
\ No newline at end of file
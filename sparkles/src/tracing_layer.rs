@@ -0,0 +1,270 @@
+//! `tracing` integration: spans are recorded as sparkles ranges, events as instant events.
+//!
+//! Metadata of every callsite is interned once into a static `EventMeta`, so recording an event costs
+//! a thread-local hash map lookup on top of the usual sparkles event.
+//! Numeric and boolean fields are recorded as event arguments. For events, `message` and other string and debug fields
+//! are formatted into the attached text message, like `message key=value`. String and debug fields of spans are skipped.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::sync::Mutex;
+use tracing_core::callsite::Identifier;
+use tracing_core::field::{Field, Visit};
+use tracing_core::span::{Attributes, Id, Record};
+use tracing_core::{Event, Metadata, Subscriber};
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+use crate::{event_hash, EventArg, EventMeta, RangeStartGuard, SourceLocation};
+
+/// Fields with greater index are not recorded. `tracing` doesn't allow more fields in a single callsite anyway
+const MAX_FIELDS: usize = 32;
+
+/// Callsite and the set of recorded fields -> interned event
+type InternKey = (Identifier, u64);
+type InternedEvent = (u32, &'static EventMeta<'static>);
+
+static INTERNED: Mutex<Option<HashMap<InternKey, InternedEvent>>> = Mutex::new(None);
+
+thread_local! {
+    static INTERNED_CACHE: RefCell<HashMap<InternKey, InternedEvent>> = RefCell::new(HashMap::new());
+    /// Spans, entered on the current thread
+    static ENTERED_SPANS: RefCell<EnteredSpans> = const { RefCell::new(EnteredSpans(Vec::new())) };
+}
+
+/// Ranges of the entered spans
+struct EnteredSpans(Vec<(Id, RangeStartGuard)>);
+
+impl Drop for EnteredSpans {
+    fn drop(&mut self) {
+        // Thread is exiting and the thread-local storage may be already destroyed, spans left entered are not finished
+        for (_, guard) in self.0.drain(..) {
+            std::mem::forget(guard);
+        }
+    }
+}
+
+/// Get event hash and metadata for the callsite with given set of recorded fields
+fn intern(metadata: &'static Metadata<'static>, mask: u64) -> InternedEvent {
+    let key = (metadata.callsite(), mask);
+    if let Some(interned) = INTERNED_CACHE.with_borrow(|cache| cache.get(&key).copied()) {
+        return interned;
+    }
+
+    let interned = *INTERNED.lock().unwrap().get_or_insert_with(HashMap::new).entry(key.clone()).or_insert_with(|| {
+        let arg_names: Vec<&'static str> = metadata.fields().iter()
+            .filter(|field| field.index() < MAX_FIELDS && mask & (1 << field.index()) != 0)
+            .map(|field| field.name())
+            .collect();
+
+        let mut hash_key = metadata.name().to_string();
+        for arg_name in &arg_names {
+            hash_key.push('\0');
+            hash_key.push_str(arg_name);
+        }
+        hash_key.push_str(&format!("\0{}\0{}:{}", metadata.target(), metadata.file().unwrap_or(""), metadata.line().unwrap_or(0)));
+
+        let location = match (metadata.module_path(), metadata.file(), metadata.line()) {
+            (Some(module_path), Some(file), Some(line)) => Some(SourceLocation::new(module_path, file, line)),
+            _ => None,
+        };
+        let meta = Box::leak(Box::new(EventMeta {
+            name: metadata.name(),
+            arg_names: Box::leak(arg_names.into_boxed_slice()),
+            location,
+            category: None,
+            is_static: true,
+        }));
+        (event_hash(&hash_key), &*meta)
+    });
+
+    INTERNED_CACHE.with_borrow_mut(|cache| cache.insert(key, interned));
+    interned
+}
+
+/// Numeric field values, indexed by field index, and text of the other fields
+struct FieldValues {
+    mask: u64,
+    values: [EventArg; MAX_FIELDS],
+    message: String,
+}
+
+impl FieldValues {
+    fn new() -> Self {
+        Self {
+            mask: 0,
+            values: [EventArg::Unsigned(0); MAX_FIELDS],
+            message: String::new(),
+        }
+    }
+
+    /// Append field to the text message. `message` field goes first without the name
+    fn write_text(&mut self, field: &Field, value: std::fmt::Arguments) {
+        if field.name() == "message" {
+            let rest = std::mem::take(&mut self.message);
+            let _ = self.message.write_fmt(value);
+            self.message.push_str(&rest);
+        }
+        else {
+            let _ = write!(self.message, " {}={}", field.name(), value);
+        }
+    }
+
+    fn set(&mut self, field: &Field, value: EventArg) {
+        if field.index() < MAX_FIELDS {
+            self.mask |= 1 << field.index();
+            self.values[field.index()] = value;
+        }
+    }
+
+    /// Write recorded values in the order of field indices, returns amount of values
+    fn compact(&self, out: &mut [EventArg; MAX_FIELDS]) -> usize {
+        let mut len = 0;
+        let mut mask = self.mask;
+        while mask != 0 {
+            out[len] = self.values[mask.trailing_zeros() as usize];
+            len += 1;
+            mask &= mask - 1;
+        }
+        len
+    }
+}
+
+impl Visit for FieldValues {
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.set(field, value.into());
+    }
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.set(field, value.into());
+    }
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.set(field, value.into());
+    }
+    fn record_i128(&mut self, field: &Field, value: i128) {
+        self.set(field, EventArg::Signed(value as i64));
+    }
+    fn record_u128(&mut self, field: &Field, value: u128) {
+        self.set(field, EventArg::Unsigned(value as u64));
+    }
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.set(field, EventArg::Unsigned(value as u64));
+    }
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.write_text(field, format_args!("{}", value));
+    }
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.write_text(field, format_args!("{:?}", value));
+    }
+}
+
+/// Data of the span, stored in its extensions
+struct SpanEvent {
+    hash: u32,
+    meta: &'static EventMeta<'static>,
+    values: FieldValues,
+    args: Vec<EventArg>,
+}
+
+impl SpanEvent {
+    fn new(metadata: &'static Metadata<'static>, mut values: FieldValues) -> Self {
+        // Ranges can't have attached message
+        values.message = String::new();
+        let (hash, meta) = intern(metadata, values.mask);
+        let mut args = [EventArg::Unsigned(0); MAX_FIELDS];
+        let len = values.compact(&mut args);
+        Self {
+            hash,
+            meta,
+            values,
+            args: args[..len].to_vec(),
+        }
+    }
+
+    /// Resolve event for the current set of recorded fields
+    fn update(&mut self, metadata: &'static Metadata<'static>) {
+        let values = std::mem::replace(&mut self.values, FieldValues::new());
+        *self = Self::new(metadata, values);
+    }
+}
+
+/// `tracing_subscriber` layer, which records entered spans as sparkles ranges and events as instant events.
+///
+/// ```rust,ignore
+/// use tracing_subscriber::prelude::*;
+///
+/// let _finalize_guard = sparkles::init_default();
+/// tracing_subscriber::registry().with(sparkles::SparklesLayer::new()).init();
+/// ```
+#[derive(Default)]
+pub struct SparklesLayer;
+
+impl SparklesLayer {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl<S> Layer<S> for SparklesLayer
+where S: Subscriber + for<'a> LookupSpan<'a> {
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut values = FieldValues::new();
+        attrs.record(&mut values);
+        span.extensions_mut().insert(SpanEvent::new(attrs.metadata(), values));
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut extensions = span.extensions_mut();
+        if let Some(span_event) = extensions.get_mut::<SpanEvent>() {
+            values.record(&mut span_event.values);
+            span_event.update(span.metadata());
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let mut values = FieldValues::new();
+        event.record(&mut values);
+        let (hash, meta) = intern(event.metadata(), values.mask);
+        let mut args = [EventArg::Unsigned(0); MAX_FIELDS];
+        let len = values.compact(&mut args);
+        let message = values.message.trim_start();
+        if message.is_empty() {
+            crate::instant_event_with_args(hash, meta, &args[..len]);
+        }
+        else {
+            crate::instant_event_with_message(hash, meta, &args[..len], message);
+        }
+    }
+
+    fn on_enter(&self, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let extensions = span.extensions();
+        if let Some(span_event) = extensions.get::<SpanEvent>() {
+            // Nothing is recorded, if the span is entered after thread-local destructors started
+            let _ = ENTERED_SPANS.try_with(|spans| {
+                let guard = crate::range_event_start_with_args(span_event.hash, span_event.meta, &span_event.args);
+                spans.borrow_mut().0.push((id.clone(), guard));
+            });
+        }
+    }
+
+    fn on_exit(&self, id: &Id, _ctx: Context<'_, S>) {
+        let guard = ENTERED_SPANS.try_with(|spans| {
+            let mut spans = spans.borrow_mut();
+            let pos = spans.0.iter().rposition(|(span_id, _)| span_id == id)?;
+            Some(spans.0.remove(pos).1)
+        }).ok().flatten();
+        // Range end is recorded outside of the borrow
+        if let Some(guard) = guard {
+            guard.try_end();
+        }
+    }
+}